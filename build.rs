@@ -2,10 +2,17 @@ use anyhow::*;
 use fs_extra::copy_items;
 use fs_extra::dir::CopyOptions;
 use std::env;
+use std::path::Path;
+
+#[path = "src/preprocessor.rs"]
+#[allow(dead_code)]
+mod preprocessor;
 
 fn main() -> Result<()> {
     // This tells cargo to rerun this script if something in res/ changes.
     println!("cargo:rerun-if-changed=res/*");
+    println!("cargo:rerun-if-changed=src/shaders");
+    println!("cargo:rerun-if-changed=src/preprocessor.rs");
 
     // Prepare what to copy and how
     let mut copy_options = CopyOptions::new();
@@ -14,7 +21,9 @@ fn main() -> Result<()> {
 
     // Copy the items to the directory where the executable will be built
     let out_dir = env::var("OUT_DIR")?;
-    copy_items(&paths_to_copy, out_dir, &copy_options)?;
+    copy_items(&paths_to_copy, &out_dir, &copy_options)?;
+
+    preprocess_shaders(Path::new("src/shaders"), &Path::new(&out_dir).join("shaders"))?;
 
     // Copy the items to the directory where they will be hosted
     // - The out_dir will likely be different in your project
@@ -28,3 +37,24 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+// Resolves the preprocessor directives of every shader so the binary can embed
+// plain WGSL with `include_str!`.
+fn preprocess_shaders(src_dir: &Path, out_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(out_dir)?;
+
+    for entry in std::fs::read_dir(src_dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "wgsl") {
+            continue;
+        }
+
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        let preprocessed = preprocessor::preprocess(src_dir, &name)?;
+        std::fs::write(out_dir.join(&name), preprocessed.source)?;
+    }
+
+    Ok(())
+}
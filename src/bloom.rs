@@ -0,0 +1,382 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    postprocess::HDR_FORMAT,
+    renderer::{create_render_pipeline, PipelineState},
+    shaders::ShaderId,
+    texture,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    /// How much of the blurred highlights is added back onto the scene.
+    pub intensity: f32,
+    /// Brightness above which a pixel starts to glow, before exposure.
+    pub threshold: f32,
+    /// Spread of the upsampling taps, in texels of the mip being upsampled.
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensity: 0.05,
+            threshold: 1.0,
+            radius: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Pod, Zeroable)]
+pub struct BloomUniform {
+    texel_size: [f32; 2],
+    threshold: f32,
+    radius: f32,
+    prefilter: u32,
+    _padding: u32,
+}
+
+/// The bind group sampling a texture, along with the uniform of the pass that
+/// reads it.
+struct Source {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+struct Mip {
+    texture: texture::Texture,
+    source: Source,
+}
+
+/// Thresholds the HDR scene into a chain of successively halved targets, then
+/// walks back up it, adding each level onto the next larger one and finally
+/// onto the scene.
+pub struct Bloom {
+    pub settings: BloomSettings,
+    uploaded: Option<(BloomSettings, u32, u32)>,
+    width: u32,
+    height: u32,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    downsample_pipeline: wgpu::RenderPipeline,
+    upsample_pipeline: wgpu::RenderPipeline,
+    /// Reads the HDR scene for the first downsample.
+    scene: Source,
+    mips: Vec<Mip>,
+}
+
+impl Bloom {
+    const MAX_MIPS: u32 = 6;
+    /// Smallest size a mip is allowed to have along either axis.
+    const MIN_MIP_SIZE: u32 = 8;
+
+    pub fn new(device: &wgpu::Device, hdr: &texture::Texture, width: u32, height: u32) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bloom_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let downsample_pipeline = create_bloom_pipeline(
+            device,
+            &pipeline_layout,
+            ShaderId::BloomDownsample,
+            ShaderId::BloomDownsample.descriptor(ShaderId::BloomDownsample.embedded()),
+        );
+        let upsample_pipeline = create_bloom_pipeline(
+            device,
+            &pipeline_layout,
+            ShaderId::BloomUpsample,
+            ShaderId::BloomUpsample.descriptor(ShaderId::BloomUpsample.embedded()),
+        );
+
+        let scene = create_source(device, &bind_group_layout, hdr, "bloom_scene");
+        let mips = create_mips(device, &bind_group_layout, width, height);
+
+        Self {
+            settings: BloomSettings::default(),
+            uploaded: None,
+            width,
+            height,
+            bind_group_layout,
+            pipeline_layout,
+            downsample_pipeline,
+            upsample_pipeline,
+            scene,
+            mips,
+        }
+    }
+
+    /// Must be called whenever the HDR target is recreated.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        hdr: &texture::Texture,
+        width: u32,
+        height: u32,
+    ) {
+        self.width = width;
+        self.height = height;
+        self.scene = create_source(device, &self.bind_group_layout, hdr, "bloom_scene");
+        self.mips = create_mips(device, &self.bind_group_layout, width, height);
+        self.uploaded = None;
+    }
+
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        shader: ShaderId,
+        descriptor: wgpu::ShaderModuleDescriptor,
+    ) -> wgpu::RenderPipeline {
+        create_bloom_pipeline(device, &self.pipeline_layout, shader, descriptor)
+    }
+
+    pub fn set_pipeline(&mut self, shader: ShaderId, pipeline: wgpu::RenderPipeline) {
+        match shader {
+            ShaderId::BloomDownsample => self.downsample_pipeline = pipeline,
+            ShaderId::BloomUpsample => self.upsample_pipeline = pipeline,
+            _ => {}
+        }
+    }
+
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        let key = (self.settings, self.width, self.height);
+        if self.uploaded == Some(key) {
+            return;
+        }
+
+        let mut source_size = (self.width, self.height);
+        let mip_sources = self.mips.iter().map(|mip| &mip.source);
+        for (i, source) in std::iter::once(&self.scene).chain(mip_sources).enumerate() {
+            let uniform = BloomUniform {
+                texel_size: [1.0 / source_size.0 as f32, 1.0 / source_size.1 as f32],
+                threshold: self.settings.threshold,
+                radius: self.settings.radius,
+                prefilter: (i == 0) as u32,
+                _padding: 0,
+            };
+            queue.write_buffer(&source.buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+            if let Some(next) = self.mips.get(i) {
+                source_size = (next.texture.texture.width(), next.texture.texture.height());
+            }
+        }
+
+        self.uploaded = Some(key);
+    }
+
+    /// Adds the bloom onto `hdr`, which must be the target passed to `new` or
+    /// the last `resize`.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, hdr: &wgpu::TextureView) {
+        if !self.settings.enabled || self.mips.is_empty() {
+            return;
+        }
+
+        let mut source = &self.scene;
+        for mip in &self.mips {
+            run_pass(
+                encoder,
+                "Bloom Downsample",
+                &self.downsample_pipeline,
+                &source.bind_group,
+                &mip.texture.view,
+                None,
+            );
+            source = &mip.source;
+        }
+
+        for (i, mip) in self.mips.iter().enumerate().rev() {
+            let (target, blend) = match i {
+                0 => (hdr, self.settings.intensity as f64),
+                _ => (&self.mips[i - 1].texture.view, 1.0),
+            };
+            run_pass(
+                encoder,
+                "Bloom Upsample",
+                &self.upsample_pipeline,
+                &mip.source.bind_group,
+                target,
+                Some(blend),
+            );
+        }
+    }
+}
+
+/// Clears the target unless `blend` is set, in which case the output is
+/// scaled by it and added onto the target.
+fn run_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    target: &wgpu::TextureView,
+    blend: Option<f64>,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: match blend {
+                    Some(_) => wgpu::LoadOp::Load,
+                    None => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                },
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    if let Some(blend) = blend {
+        render_pass.set_blend_constant(wgpu::Color {
+            r: blend,
+            g: blend,
+            b: blend,
+            a: blend,
+        });
+    }
+    render_pass.draw(0..3, 0..1);
+}
+
+fn create_bloom_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: ShaderId,
+    descriptor: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    // Upsampling adds onto the target, scaled by the blend constant so the
+    // same pipeline serves the mip chain and the final combine.
+    let additive = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Constant,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    };
+    let blend = match shader {
+        ShaderId::BloomUpsample => wgpu::BlendState {
+            color: additive,
+            alpha: additive,
+        },
+        _ => wgpu::BlendState::REPLACE,
+    };
+
+    create_render_pipeline(
+        device,
+        layout,
+        HDR_FORMAT,
+        &[],
+        PipelineState {
+            cull_mode: None,
+            blend,
+            ..Default::default()
+        },
+        descriptor,
+    )
+}
+
+fn create_source(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &texture::Texture,
+    label: &str,
+) -> Source {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: std::mem::size_of::<BloomUniform>() as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    Source { buffer, bind_group }
+}
+
+fn create_mips(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    width: u32,
+    height: u32,
+) -> Vec<Mip> {
+    let mut mips = Vec::new();
+    let (mut w, mut h) = (width / 2, height / 2);
+    while mips.len() < Bloom::MAX_MIPS as usize && w.min(h) >= Bloom::MIN_MIP_SIZE {
+        let label = format!("bloom_mip_{}", mips.len());
+        let texture = texture::Texture::create_render_target(device, w, h, HDR_FORMAT, &label);
+        let source = create_source(device, layout, &texture, &label);
+        mips.push(Mip { texture, source });
+        w /= 2;
+        h /= 2;
+    }
+
+    mips
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BloomUniform;
+    use crate::layout;
+
+    #[test]
+    fn uniform_matches_wgsl() {
+        layout::assert_struct_fields!(
+            BloomUniform,
+            "BloomSettings",
+            [texel_size, threshold, radius, prefilter, _padding]
+        );
+    }
+}
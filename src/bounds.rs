@@ -0,0 +1,154 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Self {
+        let mut min = Point3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Point3::new(f32::MIN, f32::MIN, f32::MIN);
+
+        for [x, y, z] in points {
+            min = Point3::new(min.x.min(x), min.y.min(y), min.z.min(z));
+            max = Point3::new(max.x.max(x), max.y.max(y), max.z.max(z));
+        }
+
+        if min.x > max.x {
+            let origin = Point3::new(0.0, 0.0, 0.0);
+            return Self {
+                min: origin,
+                max: origin,
+            };
+        }
+
+        Self { min, max }
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// The box enclosing this one once transformed, which grows under
+    /// rotation.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        Self::from_points(
+            self.corners()
+                .map(|corner| matrix.transform_point(corner).into()),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl Sphere {
+    /// Centered on the points' bounding box, which is not the smallest sphere
+    /// but is cheap and close enough for culling.
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]> + Clone) -> Self {
+        let center = Aabb::from_points(points.clone()).center();
+        let radius = points
+            .into_iter()
+            .map(|point| center.distance2(point.into()))
+            .fold(0.0, f32::max)
+            .sqrt();
+
+        Self { center, radius }
+    }
+
+    /// Scales the radius by the largest axis scale, so the sphere still
+    /// encloses the same points under non-uniform scaling.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let scale = [matrix.x, matrix.y, matrix.z]
+            .map(|axis| axis.truncate().magnitude())
+            .into_iter()
+            .fold(0.0, f32::max);
+
+        Self {
+            center: matrix.transform_point(self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::prelude::*;
+    use cgmath::{Deg, Matrix4, Point3, Vector3};
+
+    use super::{Aabb, Sphere};
+
+    fn assert_near(actual: Point3<f32>, expected: Point3<f32>) {
+        assert!(
+            (actual - expected).magnitude() < 1e-5,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn empty_points_give_an_empty_box_at_the_origin() {
+        let aabb = Aabb::from_points([]);
+        assert_eq!(aabb.min, Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(aabb.max, Point3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn transformed_boxes_enclose_the_transformed_corners() {
+        let aabb = Aabb::from_points([[-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]]);
+
+        let moved = aabb.transformed(&Matrix4::from_translation(Vector3::new(2.0, 0.0, -3.0)));
+        assert_near(moved.min, Point3::new(1.0, -1.0, -4.0));
+        assert_near(moved.max, Point3::new(3.0, 1.0, -2.0));
+
+        // Turned 45° around Y, the corners reach out to sqrt(2) in X and Z.
+        let turned = aabb.transformed(&Matrix4::from_angle_y(Deg(45.0)));
+        let r = 2f32.sqrt();
+        assert_near(turned.min, Point3::new(-r, -1.0, -r));
+        assert_near(turned.max, Point3::new(r, 1.0, r));
+    }
+
+    #[test]
+    fn spheres_enclose_their_points() {
+        let points = [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [1.0, 1.0, 0.0]];
+        let sphere = Sphere::from_points(points);
+        assert_near(sphere.center, Point3::new(1.0, 0.5, 0.0));
+        for point in points {
+            assert!(sphere.center.distance(point.into()) <= sphere.radius + 1e-5);
+        }
+    }
+
+    #[test]
+    fn transformed_spheres_grow_with_the_largest_scale() {
+        let sphere = Sphere {
+            center: Point3::new(1.0, 0.0, 0.0),
+            radius: 2.0,
+        };
+        let matrix = Matrix4::from_translation(Vector3::new(0.0, 5.0, 0.0))
+            * Matrix4::from_angle_z(Deg(90.0))
+            * Matrix4::from_nonuniform_scale(1.0, 3.0, 2.0);
+
+        let transformed = sphere.transformed(&matrix);
+        assert_near(transformed.center, Point3::new(0.0, 6.0, 0.0));
+        assert!((transformed.radius - 6.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+use bytemuck::Pod;
+
+const MIN_CAPACITY: usize = 16;
+
+/// A GPU buffer mirroring a CPU-side `Vec<T>` that grows by doubling and only
+/// re-uploads the elements that changed since the last `upload`.
+pub struct DynamicBuffer<T: Pod> {
+    label: String,
+    usage: wgpu::BufferUsages,
+    buffer: wgpu::Buffer,
+    capacity: usize,
+    data: Vec<T>,
+    dirty: Option<Range<usize>>,
+}
+
+impl<T: Pod> DynamicBuffer<T> {
+    pub fn new(device: &wgpu::Device, label: &str, usage: wgpu::BufferUsages) -> Self {
+        Self::with_data(device, label, usage, Vec::new())
+    }
+
+    pub fn with_data(
+        device: &wgpu::Device,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        data: Vec<T>,
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let capacity = data.len().next_power_of_two().max(MIN_CAPACITY);
+        let buffer = Self::allocate(device, label, usage, capacity);
+        let dirty = (!data.is_empty()).then_some(0..data.len());
+
+        Self {
+            label: label.to_owned(),
+            usage,
+            buffer,
+            capacity,
+            data,
+            dirty,
+        }
+    }
+
+    fn allocate(
+        device: &wgpu::Device,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        capacity: usize,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        self.dirty = Some(merge_dirty(self.dirty.take(), range));
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.data.push(item);
+        self.mark_dirty(self.data.len() - 1..self.data.len());
+    }
+
+    pub fn replace(&mut self, data: impl IntoIterator<Item = T>) {
+        self.data.clear();
+        self.data.extend(data);
+        self.dirty = (!self.data.is_empty()).then_some(0..self.data.len());
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.dirty = None;
+    }
+
+    /// Writes pending changes to the GPU. Returns `true` when the underlying
+    /// buffer had to be reallocated, so bind groups referencing it must be
+    /// rebuilt.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        let mut reallocated = false;
+
+        if self.data.len() > self.capacity {
+            self.capacity = grown_capacity(self.capacity, self.data.len());
+            self.buffer = Self::allocate(device, &self.label, self.usage, self.capacity);
+            self.dirty = Some(0..self.data.len());
+            reallocated = true;
+        }
+
+        if let Some(dirty) = self.dirty.take() {
+            let end = dirty.end.min(self.data.len());
+            if dirty.start < end {
+                let offset = (dirty.start * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+                queue.write_buffer(
+                    &self.buffer,
+                    offset,
+                    bytemuck::cast_slice(&self.data[dirty.start..end]),
+                );
+            }
+        }
+
+        reallocated
+    }
+
+    /// The part of the buffer holding live elements. Must not be called when
+    /// the buffer is empty.
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        let size = (self.data.len() * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+        self.buffer.slice(..size)
+    }
+}
+
+/// The smallest range covering both the pending `dirty` range and `range`.
+fn merge_dirty(dirty: Option<Range<usize>>, range: Range<usize>) -> Range<usize> {
+    match dirty {
+        Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+        None => range,
+    }
+}
+
+/// `capacity` doubled until it holds `len` elements.
+fn grown_capacity(mut capacity: usize, len: usize) -> usize {
+    while capacity < len {
+        capacity *= 2;
+    }
+    capacity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grown_capacity, merge_dirty, MIN_CAPACITY};
+
+    #[test]
+    fn dirty_ranges_merge_into_their_span() {
+        assert_eq!(merge_dirty(None, 3..4), 3..4);
+        assert_eq!(merge_dirty(Some(3..4), 1..2), 1..4);
+        assert_eq!(merge_dirty(Some(1..4), 2..3), 1..4);
+        assert_eq!(merge_dirty(Some(1..2), 8..9), 1..9);
+    }
+
+    #[test]
+    fn capacity_doubles_until_it_fits() {
+        assert_eq!(grown_capacity(MIN_CAPACITY, MIN_CAPACITY), MIN_CAPACITY);
+        assert_eq!(
+            grown_capacity(MIN_CAPACITY, MIN_CAPACITY + 1),
+            MIN_CAPACITY * 2
+        );
+        assert_eq!(
+            grown_capacity(MIN_CAPACITY, MIN_CAPACITY * 5),
+            MIN_CAPACITY * 8
+        );
+    }
+}
@@ -6,6 +6,8 @@ use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
 #[derive(Debug, Copy, Clone, Pod, Zeroable)]
 pub struct CameraUniform {
     view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+    view_position: [f32; 4],
 }
 
 impl CameraUniform {
@@ -13,20 +15,30 @@ impl CameraUniform {
         use cgmath::SquareMatrix;
         Self {
             view_proj: cgmath::Matrix4::identity().into(),
+            inv_view_proj: cgmath::Matrix4::identity().into(),
+            view_position: [0.0; 4],
         }
     }
 
     pub fn update_view_proj(&mut self, camera: &Camera) {
-        self.view_proj = camera.build_view_projection_matrix().into();
+        use cgmath::SquareMatrix;
+        let view_proj = camera.build_view_projection_matrix();
+        self.view_proj = view_proj.into();
+        if let Some(inv_view_proj) = view_proj.invert() {
+            self.inv_view_proj = inv_view_proj.into();
+        }
+        self.view_position = camera.eye.to_homogeneous().into();
     }
 }
 
+/// Maps OpenGL's -1..1 clip space depth to wgpu's 0..1, `z' = (z + w) / 2`.
+/// The arguments are columns.
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
     0.0, 1.0, 0.0, 0.0,
-    0.0, 0.0, 0.5, 0.5,
-    0.0, 0.0, 0.0, 1.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
 );
 
 #[derive(Debug, Clone, Copy)]
@@ -41,6 +53,47 @@ pub struct Camera {
 }
 
 impl Camera {
+    pub const FOVY_RANGE: std::ops::RangeInclusive<f32> = 1.0..=179.0;
+    pub const MIN_ZNEAR: f32 = 0.001;
+
+    /// Why the projection or view would be degenerate, if it would.
+    pub fn validate(&self) -> Result<(), String> {
+        use cgmath::InnerSpace;
+
+        if [self.aspect, self.fovy, self.znear, self.zfar]
+            .iter()
+            .any(|value| !value.is_finite())
+        {
+            return Err("values must be finite".into());
+        }
+        if self.aspect <= 0.0 {
+            return Err("aspect must be positive".into());
+        }
+        if !Self::FOVY_RANGE.contains(&self.fovy) {
+            return Err(format!(
+                "fovy must be between {}° and {}°",
+                Self::FOVY_RANGE.start(),
+                Self::FOVY_RANGE.end()
+            ));
+        }
+        if self.znear < Self::MIN_ZNEAR {
+            return Err(format!("z near must be at least {}", Self::MIN_ZNEAR));
+        }
+        if self.znear >= self.zfar {
+            return Err("z near must be less than z far".into());
+        }
+
+        let forward = self.target - self.eye;
+        if forward.magnitude2() < f32::EPSILON {
+            return Err("eye and target must differ".into());
+        }
+        if forward.normalize().cross(self.up).magnitude2() < f32::EPSILON {
+            return Err("up must not be parallel to the view direction".into());
+        }
+
+        Ok(())
+    }
+
     pub fn build_view_projection_matrix(&self) -> Matrix4<f32> {
         let view = Matrix4::look_at_rh(self.eye, self.target, self.up);
 
@@ -48,6 +101,17 @@ impl Camera {
 
         OPENGL_TO_WGPU_MATRIX * proj * view
     }
+
+    /// The camera as seen from outside a node placed at `matrix`.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        use cgmath::{InnerSpace, Transform};
+        Self {
+            eye: matrix.transform_point(self.eye),
+            target: matrix.transform_point(self.target),
+            up: matrix.transform_vector(self.up).normalize(),
+            ..*self
+        }
+    }
 }
 
 pub struct CameraController {
@@ -138,3 +202,71 @@ impl CameraController {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Point3, Vector3};
+
+    use super::{Camera, CameraUniform};
+    use crate::layout;
+
+    fn camera() -> Camera {
+        Camera {
+            eye: Point3::new(0.0, 1.0, 2.0),
+            target: Point3::new(0.0, 0.0, 0.0),
+            up: Vector3::unit_y(),
+            aspect: 16.0 / 9.0,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        }
+    }
+
+    #[test]
+    fn validate_rejects_degenerate_cameras() {
+        assert_eq!(camera().validate(), Ok(()));
+
+        let invalid = [
+            Camera {
+                aspect: 0.0,
+                ..camera()
+            },
+            Camera {
+                fovy: 180.0,
+                ..camera()
+            },
+            Camera {
+                znear: 0.0,
+                ..camera()
+            },
+            Camera {
+                znear: 100.0,
+                ..camera()
+            },
+            Camera {
+                zfar: f32::INFINITY,
+                ..camera()
+            },
+            Camera {
+                target: Point3::new(0.0, 1.0, 2.0),
+                ..camera()
+            },
+            Camera {
+                up: Vector3::new(0.0, -1.0, -2.0),
+                ..camera()
+            },
+        ];
+        for camera in invalid {
+            assert!(camera.validate().is_err(), "{camera:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn uniform_matches_wgsl() {
+        layout::assert_struct_fields!(
+            CameraUniform,
+            "CameraUniform",
+            [view_proj, inv_view_proj, view_position]
+        );
+    }
+}
@@ -9,6 +9,8 @@ use egui::ClippedPrimitive;
 use egui_wgpu::renderer::ScreenDescriptor;
 use egui_winit::State;
 
+use std::time::Instant;
+
 use anyhow::{Ok, Result};
 use winit::{event::WindowEvent, event_loop::EventLoop, window::Window};
 
@@ -34,9 +36,10 @@ impl Core {
         //     window.inner_size().height as f32,
         // );
 
-        let ui = UI::new();
+        let mut ui = UI::new();
 
         let initial_status = AppStatus::Info("Init Done!".to_owned());
+        ui.notify(initial_status.clone());
 
         let mut state = State::new(&event_loop);
         state.set_pixels_per_point(scale_factor);
@@ -69,6 +72,11 @@ impl Core {
     }
 
     pub fn render(&mut self, window: &Window) -> Result<(), wgpu::SurfaceError> {
+        for status in self.renderer.take_statuses() {
+            self.ui.notify(status.clone());
+            self.status = status;
+        }
+
         let mut ui_state = UiState {
             is_paused: false,
             status: self.status.clone(),
@@ -76,19 +84,32 @@ impl Core {
 
         let raw_input = self.state.take_egui_input(window);
 
+        let start = Instant::now();
         let full_output = self.ui.prepare(
             raw_input,
             &self.event_proxy,
             &mut ui_state,
-            &mut self.renderer.camera(),
             &mut self.renderer.mouse(),
+            &mut self.renderer,
         );
+        self.renderer.profiler.record("UI", start);
+        // Camera edits made in the UI.
+        self.renderer.update_camera();
+
+        if self.renderer.mouse().clicked()
+            && !self.ui.context().wants_pointer_input()
+            && !self.renderer.begin_gizmo_drag()
+        {
+            self.renderer.pick_at_cursor();
+        }
 
         self.state
             .handle_platform_output(window, self.ui.context(), full_output.platform_output);
 
+        let start = Instant::now();
         let clipped_primitives: &[ClippedPrimitive] =
             &self.ui.context().tessellate(full_output.shapes);
+        self.renderer.profiler.record("Tessellate", start);
 
         let screen_descriptor = ScreenDescriptor {
             size_in_pixels: [self.renderer.size.width, self.renderer.size.height],
@@ -0,0 +1,508 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    renderer::{create_render_pipeline, PipelineState},
+    shaders::ShaderId,
+    texture,
+};
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTERED_SIZE: u32 = 128;
+/// Roughness goes from 0 at the top mip to 1 at the last one.
+const PREFILTERED_MIPS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvironmentSettings {
+    pub enabled: bool,
+    pub intensity: f32,
+}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensity: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct EnvironmentUniform {
+    intensity: f32,
+    max_lod: f32,
+    enabled: u32,
+    _padding: u32,
+}
+
+impl From<EnvironmentSettings> for EnvironmentUniform {
+    fn from(settings: EnvironmentSettings) -> Self {
+        Self {
+            intensity: settings.intensity,
+            max_lod: (PREFILTERED_MIPS - 1) as f32,
+            enabled: settings.enabled as u32,
+            _padding: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct PrefilterUniform {
+    roughness: f32,
+    /// Of the top mip of the source.
+    source_size: f32,
+    max_lod: f32,
+    _padding: f32,
+}
+
+impl PrefilterUniform {
+    fn new(roughness: f32, source: &texture::Texture) -> Self {
+        Self {
+            roughness,
+            source_size: source.texture.width() as f32,
+            max_lod: (source.texture.mip_level_count() - 1) as f32,
+            _padding: 0.0,
+        }
+    }
+}
+
+/// Image-based lighting: an irradiance map for diffuse light, a prefiltered
+/// map with one roughness per mip for specular light, and the BRDF lookup
+/// table of the split-sum approximation. The maps are generated on the GPU
+/// from an environment cubemap and bound with every material, at bindings 2
+/// to 6 of its group.
+pub struct Environment {
+    pub settings: EnvironmentSettings,
+    uploaded: Option<EnvironmentSettings>,
+    buffer: wgpu::Buffer,
+    irradiance: texture::Texture,
+    prefiltered: texture::Texture,
+    brdf_lut: texture::Texture,
+    bind_group_layout: wgpu::BindGroupLayout,
+    cube_pipeline_layout: wgpu::PipelineLayout,
+    lut_pipeline_layout: wgpu::PipelineLayout,
+    irradiance_pipeline: wgpu::RenderPipeline,
+    prefilter_pipeline: wgpu::RenderPipeline,
+    brdf_lut_pipeline: wgpu::RenderPipeline,
+}
+
+impl Environment {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, source: &texture::Texture) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Environment Buffer"),
+            size: std::mem::size_of::<EnvironmentUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let irradiance =
+            texture::Texture::create_cubemap(device, IRRADIANCE_SIZE, FORMAT, "irradiance_map");
+        let prefiltered = texture::Texture::create_cubemap_with_mips(
+            device,
+            PREFILTERED_SIZE,
+            PREFILTERED_MIPS,
+            FORMAT,
+            "prefiltered_map",
+        );
+        let brdf_lut = texture::Texture::create_render_target(
+            device,
+            BRDF_LUT_SIZE,
+            BRDF_LUT_SIZE,
+            wgpu::TextureFormat::Rg16Float,
+            "brdf_lut",
+        );
+
+        // Shared by the irradiance and prefilter passes, the former ignoring
+        // the uniform.
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("environment_source_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let cube_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Environment Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let lut_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("BRDF LUT Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let mut environment = Self {
+            settings: EnvironmentSettings::default(),
+            uploaded: None,
+            buffer,
+            irradiance,
+            prefiltered,
+            brdf_lut,
+            bind_group_layout,
+            irradiance_pipeline: create_environment_pipeline(
+                device,
+                &cube_pipeline_layout,
+                ShaderId::Irradiance,
+                ShaderId::Irradiance.descriptor(ShaderId::Irradiance.embedded()),
+            ),
+            prefilter_pipeline: create_environment_pipeline(
+                device,
+                &cube_pipeline_layout,
+                ShaderId::Prefilter,
+                ShaderId::Prefilter.descriptor(ShaderId::Prefilter.embedded()),
+            ),
+            brdf_lut_pipeline: create_environment_pipeline(
+                device,
+                &lut_pipeline_layout,
+                ShaderId::BrdfLut,
+                ShaderId::BrdfLut.descriptor(ShaderId::BrdfLut.embedded()),
+            ),
+            cube_pipeline_layout,
+            lut_pipeline_layout,
+        };
+
+        environment.generate(device, queue, source);
+        environment.prepare(queue);
+        environment
+    }
+
+    /// Layout entries for the bindings in `environment.wgsl`, to be added to
+    /// the material bind group layout.
+    pub fn layout_entries() -> [wgpu::BindGroupLayoutEntry; 5] {
+        let cube = wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::Cube,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        };
+        let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty,
+            count: None,
+        };
+
+        [
+            entry(2, cube),
+            entry(3, cube),
+            entry(
+                4,
+                wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+            ),
+            entry(
+                5,
+                wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            ),
+            entry(
+                6,
+                wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            ),
+        ]
+    }
+
+    /// The entries matching `layout_entries`.
+    pub fn bind_group_entries(&self) -> [wgpu::BindGroupEntry<'_>; 5] {
+        [
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&self.irradiance.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&self.prefiltered.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&self.brdf_lut.view),
+            },
+            // Linear and clamped with linear mip filtering, which suits the
+            // lookup table as well.
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Sampler(&self.prefiltered.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: self.buffer.as_entire_binding(),
+            },
+        ]
+    }
+
+    /// Renders the maps from `source`, a cubemap, in place so the bind groups
+    /// referencing them stay valid.
+    pub fn generate(&self, device: &wgpu::Device, queue: &wgpu::Queue, source: &texture::Texture) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Environment Encoder"),
+        });
+
+        let irradiance_bind_group = self.create_source_bind_group(
+            device,
+            &source.view,
+            &source.sampler,
+            PrefilterUniform::new(0.0, source),
+        );
+        for face in 0..6 {
+            let view = self.irradiance.cubemap_face_view(face, 0);
+            run_pass(
+                &mut encoder,
+                "Irradiance Pass",
+                &self.irradiance_pipeline,
+                Some(&irradiance_bind_group),
+                &view,
+                face,
+            );
+        }
+
+        let source = self.downsample(device, &mut encoder, source);
+        for mip in 0..PREFILTERED_MIPS {
+            let roughness = mip as f32 / (PREFILTERED_MIPS - 1) as f32;
+            let bind_group = self.create_source_bind_group(
+                device,
+                &source.view,
+                &source.sampler,
+                PrefilterUniform::new(roughness, &source),
+            );
+            for face in 0..6 {
+                let view = self.prefiltered.cubemap_face_view(face, mip);
+                run_pass(
+                    &mut encoder,
+                    "Prefilter Pass",
+                    &self.prefilter_pipeline,
+                    Some(&bind_group),
+                    &view,
+                    face,
+                );
+            }
+        }
+
+        run_pass(
+            &mut encoder,
+            "BRDF LUT Pass",
+            &self.brdf_lut_pipeline,
+            None,
+            &self.brdf_lut.view,
+            0,
+        );
+
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        if self.uploaded != Some(self.settings) {
+            let uniform = EnvironmentUniform::from(self.settings);
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+            self.uploaded = Some(self.settings);
+        }
+    }
+
+    /// New pipelines only take effect on the next `generate`.
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        shader: ShaderId,
+        descriptor: wgpu::ShaderModuleDescriptor,
+    ) -> wgpu::RenderPipeline {
+        let layout = match shader {
+            ShaderId::BrdfLut => &self.lut_pipeline_layout,
+            _ => &self.cube_pipeline_layout,
+        };
+        create_environment_pipeline(device, layout, shader, descriptor)
+    }
+
+    pub fn set_pipeline(&mut self, shader: ShaderId, pipeline: wgpu::RenderPipeline) {
+        match shader {
+            ShaderId::Irradiance => self.irradiance_pipeline = pipeline,
+            ShaderId::Prefilter => self.prefilter_pipeline = pipeline,
+            ShaderId::BrdfLut => self.brdf_lut_pipeline = pipeline,
+            _ => {}
+        }
+    }
+
+    /// A copy of `source` with a full mip chain, each mip averaging the one
+    /// above, for the prefilter to read the sample of each direction from the
+    /// mip matching the solid angle it stands for. Rendered by the prefilter
+    /// pipeline at roughness 0, which copies.
+    fn downsample(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &texture::Texture,
+    ) -> texture::Texture {
+        let size = source.texture.width();
+        let mips = size.ilog2() + 1;
+        let downsampled = texture::Texture::create_cubemap_with_mips(
+            device,
+            size,
+            mips,
+            FORMAT,
+            "environment_source",
+        );
+        let copy = PrefilterUniform::new(0.0, source);
+
+        for mip in 0..mips {
+            // Sampled between four texels of the mip above, the bilinear
+            // filter averages them.
+            let bind_group = if mip == 0 {
+                self.create_source_bind_group(device, &source.view, &source.sampler, copy)
+            } else {
+                let above = downsampled.cubemap_mip_view(mip - 1);
+                self.create_source_bind_group(device, &above, &downsampled.sampler, copy)
+            };
+            for face in 0..6 {
+                let view = downsampled.cubemap_face_view(face, mip);
+                run_pass(
+                    encoder,
+                    "Environment Downsample Pass",
+                    &self.prefilter_pipeline,
+                    Some(&bind_group),
+                    &view,
+                    face,
+                );
+            }
+        }
+
+        downsampled
+    }
+
+    fn create_source_bind_group(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        uniform: PrefilterUniform,
+    ) -> wgpu::BindGroup {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Prefilter Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("environment_source_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
+
+fn run_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: Option<&wgpu::BindGroup>,
+    target: &wgpu::TextureView,
+    face: u32,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+
+    render_pass.set_pipeline(pipeline);
+    if let Some(bind_group) = bind_group {
+        render_pass.set_bind_group(0, bind_group, &[]);
+    }
+    render_pass.draw(0..3, face..face + 1);
+}
+
+fn create_environment_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: ShaderId,
+    descriptor: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    let format = match shader {
+        ShaderId::BrdfLut => wgpu::TextureFormat::Rg16Float,
+        _ => FORMAT,
+    };
+
+    create_render_pipeline(
+        device,
+        layout,
+        format,
+        &[],
+        PipelineState {
+            cull_mode: None,
+            ..Default::default()
+        },
+        descriptor,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EnvironmentUniform, PrefilterUniform};
+    use crate::layout;
+
+    #[test]
+    fn uniforms_match_wgsl() {
+        layout::assert_struct_fields!(
+            EnvironmentUniform,
+            "EnvironmentSettings",
+            [intensity, max_lod, enabled, _padding]
+        );
+        layout::assert_struct_fields!(
+            PrefilterUniform,
+            "PrefilterSettings",
+            [roughness, source_size, max_lod, _padding]
+        );
+    }
+}
@@ -2,12 +2,27 @@ use winit::event_loop::EventLoopProxy;
 
 #[derive(Clone, Debug)]
 pub enum AppStatus {
-    Idle,
     Info(String),
     Warning(String),
     Error(String),
 }
 
+impl AppStatus {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::Info(_) => "ℹ",
+            Self::Warning(_) => "⚠",
+            Self::Error(_) => "🗙",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Info(message) | Self::Warning(message) | Self::Error(message) => message,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum UserEvent {
     ChangeTexture(usize),
@@ -0,0 +1,177 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector4};
+
+use crate::bounds::{Aabb, Sphere};
+
+/// The six planes bounding what a view-projection matrix sees, as
+/// `normal.xyz` and `distance.w`, with normals pointing inwards.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from the rows of the matrix (Gribb-Hartmann), with
+    /// wgpu's clip space depth going from 0 to 1.
+    pub fn from_view_proj(view_proj: &Matrix4<f32>) -> Self {
+        let row = |i: usize| view_proj.row(i);
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(2),
+            row(3) - row(2),
+        ]
+        .map(|plane| plane / plane.truncate().magnitude());
+
+        Self { planes }
+    }
+
+    fn distance(plane: &Vector4<f32>, point: Point3<f32>) -> f32 {
+        plane.truncate().dot(point.to_vec()) + plane.w
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| Self::distance(plane, sphere.center) >= -sphere.radius)
+    }
+
+    /// Conservative: a box near a corner of the frustum may pass while being
+    /// outside of it.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane's normal.
+            let corner = Point3::new(
+                if plane.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+            Self::distance(plane, corner) >= 0.0
+        })
+    }
+
+    /// Tests the cheap sphere first, then the tighter box.
+    pub fn contains_mesh(&self, sphere: &Sphere, aabb: &Aabb, model: &Matrix4<f32>) -> bool {
+        self.intersects_sphere(&sphere.transformed(model))
+            && self.intersects_aabb(&aabb.transformed(model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::prelude::*;
+    use cgmath::{Deg, Matrix4, Point3, Vector3};
+
+    use super::Frustum;
+    use crate::bounds::{Aabb, Sphere};
+    use crate::camera::OPENGL_TO_WGPU_MATRIX;
+
+    /// Looking down -Z from the origin with a 90° field of view, so the side
+    /// planes are at 45°, and depth from 1 to 10.
+    fn frustum() -> Frustum {
+        let proj = cgmath::perspective(Deg(90.0), 1.0, 1.0, 10.0);
+        Frustum::from_view_proj(&(OPENGL_TO_WGPU_MATRIX * proj))
+    }
+
+    fn sphere(x: f32, y: f32, z: f32, radius: f32) -> Sphere {
+        Sphere {
+            center: Point3::new(x, y, z),
+            radius,
+        }
+    }
+
+    fn aabb(center: [f32; 3], half: f32) -> Aabb {
+        let [x, y, z] = center;
+        Aabb {
+            min: Point3::new(x - half, y - half, z - half),
+            max: Point3::new(x + half, y + half, z + half),
+        }
+    }
+
+    #[test]
+    fn planes_are_normalized_and_face_inwards() {
+        let frustum = frustum();
+        let inside = Point3::new(0.0, 0.0, -5.0);
+        for plane in &frustum.planes {
+            assert!((plane.truncate().magnitude() - 1.0).abs() < 1e-5);
+            assert!(Frustum::distance(plane, inside) > 0.0);
+        }
+
+        // Left, right, bottom and top at 45°: at z = -5 they are 5 away.
+        for (plane, edge) in frustum.planes[..4].iter().zip([
+            Point3::new(-5.0, 0.0, -5.0),
+            Point3::new(5.0, 0.0, -5.0),
+            Point3::new(0.0, -5.0, -5.0),
+            Point3::new(0.0, 5.0, -5.0),
+        ]) {
+            assert!(Frustum::distance(plane, edge).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn near_plane_is_at_depth_zero() {
+        let frustum = frustum();
+        let near = Frustum::distance(&frustum.planes[4], Point3::new(0.0, 0.0, -1.0));
+        let far = Frustum::distance(&frustum.planes[5], Point3::new(0.0, 0.0, -10.0));
+        assert!(near.abs() < 1e-4, "near plane off by {near}");
+        assert!(far.abs() < 1e-4, "far plane off by {far}");
+
+        // Between the camera and the near plane, which -1..1 depth would keep.
+        assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, -0.5, 0.2)));
+        assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -1.1, 0.2)));
+    }
+
+    #[test]
+    fn spheres_inside_outside_and_straddling() {
+        let frustum = frustum();
+        assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -5.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(20.0, 0.0, -5.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, 5.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, -12.0, 1.0)));
+        // Across the right plane and across the far plane.
+        assert!(frustum.intersects_sphere(&sphere(5.5, 0.0, -5.0, 1.0)));
+        assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -10.5, 1.0)));
+    }
+
+    #[test]
+    fn boxes_inside_outside_and_straddling() {
+        let frustum = frustum();
+        assert!(frustum.intersects_aabb(&aabb([0.0, 0.0, -5.0], 1.0)));
+        assert!(!frustum.intersects_aabb(&aabb([-20.0, 0.0, -5.0], 1.0)));
+        assert!(!frustum.intersects_aabb(&aabb([0.0, 0.0, 5.0], 1.0)));
+        assert!(frustum.intersects_aabb(&aabb([0.0, 5.5, -5.0], 1.0)));
+        assert!(frustum.intersects_aabb(&aabb([0.0, 0.0, -1.0], 0.5)));
+    }
+
+    #[test]
+    fn meshes_are_tested_where_the_model_matrix_puts_them() {
+        let frustum = frustum();
+        let bounds = aabb([0.0, 0.0, 0.0], 1.0);
+        let sphere = sphere(0.0, 0.0, 0.0, 3f32.sqrt());
+
+        let ahead = Matrix4::from_translation(Vector3::new(0.0, 0.0, -5.0));
+        let behind = Matrix4::from_translation(Vector3::new(0.0, 0.0, 5.0));
+        assert!(frustum.contains_mesh(&sphere, &bounds, &ahead));
+        assert!(!frustum.contains_mesh(&sphere, &bounds, &behind));
+
+        // Scaled up enough to reach into view from the side.
+        let beside = Matrix4::from_translation(Vector3::new(8.0, 0.0, -5.0));
+        assert!(!frustum.contains_mesh(&sphere, &bounds, &beside));
+        let stretched = beside * Matrix4::from_nonuniform_scale(4.0, 1.0, 1.0);
+        assert!(frustum.contains_mesh(&sphere, &bounds, &stretched));
+    }
+}
@@ -0,0 +1,673 @@
+use cgmath::prelude::*;
+use cgmath::{Deg, Matrix4, Point3, Quaternion, Rad, Vector2, Vector3, Vector4};
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    buffer::DynamicBuffer,
+    camera::Camera,
+    instance::{Instance, InstanceSet},
+    model::Vertex,
+    mouse::Mouse,
+    picking::Ray,
+    renderer::{create_render_pipeline, PipelineState},
+    shaders::ShaderId,
+};
+
+// Handle sizes are fractions of the gizmo size, which itself follows the
+// camera distance so the gizmo keeps a constant size on screen.
+const SCREEN_SCALE: f32 = 0.15;
+const PICK_RADIUS_PX: f32 = 8.0;
+const RING_SEGMENTS: usize = 48;
+
+const AXIS_COLORS: [[f32; 4]; 3] = [
+    [0.9, 0.2, 0.2, 1.0],
+    [0.2, 0.9, 0.2, 1.0],
+    [0.2, 0.4, 0.95, 1.0],
+];
+const ACTIVE_COLOR: [f32; 4] = [1.0, 0.9, 0.1, 1.0];
+const CENTER_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct GizmoVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl Vertex for GizmoVertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<GizmoVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub const ALL: [GizmoMode; 3] = [Self::Translate, Self::Rotate, Self::Scale];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Translate => "Translate",
+            Self::Rotate => "Rotate",
+            Self::Scale => "Scale",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoSpace {
+    World,
+    Local,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GizmoSettings {
+    pub enabled: bool,
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    pub snap: bool,
+    pub translate_snap: f32,
+    /// Degrees.
+    pub rotate_snap: f32,
+    pub scale_snap: f32,
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mode: GizmoMode::Translate,
+            space: GizmoSpace::World,
+            snap: false,
+            translate_snap: 0.5,
+            rotate_snap: 15.0,
+            scale_snap: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handle {
+    /// Arrow or scale handle along an axis.
+    Axis(usize),
+    /// Translation in the plane whose normal is the given axis.
+    Plane(usize),
+    /// Rotation ring around an axis.
+    Ring(usize),
+    /// Uniform scale.
+    Center,
+}
+
+struct Drag {
+    handle: Handle,
+    instance: usize,
+    start: Instance,
+    start_cursor: Vector2<f32>,
+    /// Parameter along the axis, point on the plane, or vector from the
+    /// center on the rotation plane, depending on the handle.
+    anchor: Vector3<f32>,
+}
+
+pub struct Gizmo {
+    pub settings: GizmoSettings,
+    hovered: Option<Handle>,
+    drag: Option<Drag>,
+    /// Where the instance set is placed in the scene.
+    space: Matrix4<f32>,
+    vertices: DynamicBuffer<GizmoVertex>,
+    color_format: wgpu::TextureFormat,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Gizmo {
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Gizmo Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_gizmo_pipeline(
+            device,
+            &pipeline_layout,
+            color_format,
+            ShaderId::Gizmo.descriptor(ShaderId::Gizmo.embedded()),
+        );
+
+        Self {
+            settings: GizmoSettings::default(),
+            hovered: None,
+            drag: None,
+            space: Matrix4::identity(),
+            vertices: DynamicBuffer::new(device, "Gizmo Vertex Buffer", wgpu::BufferUsages::VERTEX),
+            color_format,
+            pipeline_layout,
+            pipeline,
+        }
+    }
+
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        shader: wgpu::ShaderModuleDescriptor,
+    ) -> wgpu::RenderPipeline {
+        create_gizmo_pipeline(device, &self.pipeline_layout, self.color_format, shader)
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: wgpu::RenderPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Places the instances being edited, which are relative to a scene node.
+    pub fn set_space(&mut self, space: Matrix4<f32>) {
+        self.space = space;
+    }
+
+    /// Starts dragging the handle under the cursor. Returns `false` when the
+    /// cursor is not over a handle.
+    pub fn begin_drag(&mut self, camera: &Camera, mouse: &Mouse, instances: &InstanceSet) -> bool {
+        let (Some(handle), Some(index)) = (self.hovered, instances.selected()) else {
+            return false;
+        };
+        let start = instances.instances()[index];
+        let frame = self.frame(camera, &start);
+
+        let Some(ray) = Ray::from_ndc(camera, mouse.pos_ndc()) else {
+            return false;
+        };
+        let Some(anchor) = frame.anchor(handle, &ray) else {
+            return false;
+        };
+
+        self.drag = Some(Drag {
+            handle,
+            instance: index,
+            start,
+            start_cursor: mouse.pos_viewport(),
+            anchor,
+        });
+
+        true
+    }
+
+    pub fn update(
+        &mut self,
+        camera: &Camera,
+        mouse: &Mouse,
+        viewport: Vector2<f32>,
+        instances: &mut InstanceSet,
+    ) {
+        self.vertices.clear();
+
+        let selected = instances
+            .selected()
+            .filter(|&i| self.settings.enabled && instances.instances()[i].visible);
+        let Some(index) = selected else {
+            self.drag = None;
+            self.hovered = None;
+            return;
+        };
+
+        if !mouse.pressed() || self.drag.as_ref().is_some_and(|d| d.instance != index) {
+            self.drag = None;
+        }
+
+        if let Some(drag) = &self.drag {
+            if let Some(updated) = self.apply_drag(drag, camera, mouse) {
+                if let Some(instance) = instances.get_mut(index) {
+                    *instance = updated;
+                }
+            }
+        }
+
+        let instance = instances.instances()[index];
+        let frame = self.frame(camera, &instance);
+        let view_proj = camera.build_view_projection_matrix();
+
+        self.hovered = match &self.drag {
+            Some(drag) => Some(drag.handle),
+            None => frame.hit_test(
+                self.settings.mode,
+                &view_proj,
+                viewport,
+                mouse.pos_viewport(),
+            ),
+        };
+
+        let vertices = frame.lines(self.settings.mode, self.hovered);
+        self.vertices.replace(vertices);
+    }
+
+    /// Adds lines drawn along with the handles until the next `update`.
+    pub fn extend(&mut self, lines: impl IntoIterator<Item = GizmoVertex>) {
+        for vertex in lines {
+            self.vertices.push(vertex);
+        }
+    }
+
+    fn frame(&self, camera: &Camera, instance: &Instance) -> Frame {
+        let local =
+            self.settings.space == GizmoSpace::Local || self.settings.mode == GizmoMode::Scale;
+        let axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()].map(|axis| {
+            if local {
+                self.space
+                    .transform_vector(instance.rotation.rotate_vector(axis))
+                    .normalize()
+            } else {
+                axis
+            }
+        });
+
+        let center = self
+            .space
+            .transform_point(Point3::from_vec(instance.position));
+        let size = (camera.eye - center).magnitude() * SCREEN_SCALE;
+
+        Frame { center, axes, size }
+    }
+
+    fn apply_drag(&self, drag: &Drag, camera: &Camera, mouse: &Mouse) -> Option<Instance> {
+        let settings = &self.settings;
+        let frame = self.frame(camera, &drag.start);
+        let ray = Ray::from_ndc(camera, mouse.pos_ndc())?;
+        let mut instance = drag.start;
+        // The handles work in world space, the instance in its node's space.
+        let inverse = self.space.invert()?;
+        let to_local = |vector: Vector3<f32>| inverse.transform_vector(vector);
+
+        let snap = |value: f32, step: f32| {
+            if settings.snap && step > 0.0 {
+                (value / step).round() * step
+            } else {
+                value
+            }
+        };
+
+        match (settings.mode, drag.handle) {
+            (GizmoMode::Translate, Handle::Axis(axis)) => {
+                let current = frame.anchor(drag.handle, &ray)?;
+                let delta = snap(current.x - drag.anchor.x, settings.translate_snap);
+                instance.position = drag.start.position + to_local(frame.axes[axis] * delta);
+            }
+            (GizmoMode::Translate, Handle::Plane(normal)) => {
+                let current = frame.anchor(drag.handle, &ray)?;
+                let delta = current - drag.anchor;
+                let (u, v) = plane_axes(normal);
+                instance.position = drag.start.position
+                    + to_local(
+                        frame.axes[u] * snap(delta.dot(frame.axes[u]), settings.translate_snap)
+                            + frame.axes[v]
+                                * snap(delta.dot(frame.axes[v]), settings.translate_snap),
+                    );
+            }
+            (GizmoMode::Rotate, Handle::Ring(axis)) => {
+                let current = frame.anchor(drag.handle, &ray)?;
+                let normal = frame.axes[axis];
+                let angle = Rad(drag
+                    .anchor
+                    .cross(current)
+                    .dot(normal)
+                    .atan2(drag.anchor.dot(current)));
+                let angle = Deg(snap(Deg::from(angle).0, settings.rotate_snap));
+                let normal = to_local(normal).normalize();
+                instance.rotation =
+                    (Quaternion::from_axis_angle(normal, angle) * drag.start.rotation).normalize();
+            }
+            (GizmoMode::Scale, Handle::Axis(axis)) => {
+                let current = frame.anchor(drag.handle, &ray)?;
+                if drag.anchor.x.abs() < f32::EPSILON {
+                    return None;
+                }
+                let factor = snap(current.x / drag.anchor.x, settings.scale_snap).max(0.01);
+                instance.scale[axis] = drag.start.scale[axis] * factor;
+            }
+            (GizmoMode::Scale, Handle::Center) => {
+                let dx = mouse.pos_viewport().x - drag.start_cursor.x;
+                let factor = snap(1.0 + dx / 100.0, settings.scale_snap).max(0.01);
+                instance.scale = drag.start.scale * factor;
+            }
+            _ => return None,
+        }
+
+        Some(instance)
+    }
+
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.vertices.upload(device, queue);
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertices.slice());
+        render_pass.draw(0..self.vertices.len() as u32, 0..1);
+    }
+
+    pub fn has_geometry(&self) -> bool {
+        !self.vertices.is_empty()
+    }
+}
+
+fn create_gizmo_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    // Drawn in its own pass without depth so it is never hidden.
+    create_render_pipeline(
+        device,
+        layout,
+        color_format,
+        &[GizmoVertex::desc()],
+        PipelineState {
+            topology: wgpu::PrimitiveTopology::LineList,
+            ..Default::default()
+        },
+        shader,
+    )
+}
+
+/// The other two axes of the plane whose normal is `axis`.
+fn plane_axes(axis: usize) -> (usize, usize) {
+    ((axis + 1) % 3, (axis + 2) % 3)
+}
+
+struct Frame {
+    center: Point3<f32>,
+    axes: [Vector3<f32>; 3],
+    size: f32,
+}
+
+impl Frame {
+    fn handles(mode: GizmoMode) -> Vec<Handle> {
+        match mode {
+            GizmoMode::Translate => (0..3)
+                .map(Handle::Axis)
+                .chain((0..3).map(Handle::Plane))
+                .collect(),
+            GizmoMode::Rotate => (0..3).map(Handle::Ring).collect(),
+            GizmoMode::Scale => (0..3).map(Handle::Axis).chain([Handle::Center]).collect(),
+        }
+    }
+
+    fn plane_corners(&self, normal: usize) -> [Point3<f32>; 4] {
+        let (u, v) = plane_axes(normal);
+        let (u, v) = (self.axes[u] * self.size, self.axes[v] * self.size);
+        let (near, far) = (0.2, 0.4);
+
+        [
+            self.center + u * near + v * near,
+            self.center + u * far + v * near,
+            self.center + u * far + v * far,
+            self.center + u * near + v * far,
+        ]
+    }
+
+    fn ring(&self, axis: usize) -> Vec<Point3<f32>> {
+        let (u, v) = plane_axes(axis);
+        (0..=RING_SEGMENTS)
+            .map(|i| {
+                let theta = std::f32::consts::TAU * i as f32 / RING_SEGMENTS as f32;
+                self.center + (self.axes[u] * theta.cos() + self.axes[v] * theta.sin()) * self.size
+            })
+            .collect()
+    }
+
+    /// The drag anchor for `handle` under `ray`; see `Drag::anchor`.
+    fn anchor(&self, handle: Handle, ray: &Ray) -> Option<Vector3<f32>> {
+        match handle {
+            Handle::Axis(axis) => {
+                // Closest point between the ray and the axis line.
+                let a = self.axes[axis];
+                let w = self.center - ray.origin;
+                let b = a.dot(ray.direction);
+                let denom = 1.0 - b * b;
+                if denom.abs() < 1e-6 {
+                    return None;
+                }
+                let s = (b * ray.direction.dot(w) - a.dot(w)) / denom;
+                Some(Vector3::new(s, 0.0, 0.0))
+            }
+            Handle::Plane(normal) | Handle::Ring(normal) => {
+                let n = self.axes[normal];
+                let denom = ray.direction.dot(n);
+                if denom.abs() < 1e-6 {
+                    return None;
+                }
+                let t = (self.center - ray.origin).dot(n) / denom;
+                let offset = ray.at(t) - self.center;
+                match handle {
+                    Handle::Ring(_) if offset.magnitude2() < 1e-8 => None,
+                    Handle::Ring(_) => Some(offset.normalize()),
+                    _ => Some(offset),
+                }
+            }
+            Handle::Center => Some(Vector3::zero()),
+        }
+    }
+
+    fn hit_test(
+        &self,
+        mode: GizmoMode,
+        view_proj: &Matrix4<f32>,
+        viewport: Vector2<f32>,
+        cursor: Vector2<f32>,
+    ) -> Option<Handle> {
+        let project = |p: Point3<f32>| -> Option<Vector2<f32>> {
+            let clip = view_proj * Vector4::new(p.x, p.y, p.z, 1.0);
+            if clip.w <= 0.0 {
+                return None;
+            }
+            let ndc = clip.truncate() / clip.w;
+            Some(Vector2::new(
+                (ndc.x + 1.0) / 2.0 * viewport.x,
+                (1.0 - ndc.y) / 2.0 * viewport.y,
+            ))
+        };
+
+        let polyline_distance = |points: &[Point3<f32>]| -> f32 {
+            let projected = points.iter().map(|p| project(*p)).collect::<Vec<_>>();
+            projected
+                .windows(2)
+                .filter_map(|pair| Some(segment_distance(cursor, pair[0]?, pair[1]?)))
+                .fold(f32::MAX, f32::min)
+        };
+
+        let mut best: Option<(Handle, f32)> = None;
+
+        for handle in Self::handles(mode) {
+            let distance = match handle {
+                Handle::Axis(axis) => {
+                    polyline_distance(&[self.center, self.center + self.axes[axis] * self.size])
+                }
+                Handle::Plane(normal) => {
+                    let corners = self.plane_corners(normal).map(project);
+                    match corners {
+                        [Some(a), Some(b), Some(c), Some(d)]
+                            if point_in_quad(cursor, [a, b, c, d]) =>
+                        {
+                            // Prefer axes that overlap a plane handle.
+                            PICK_RADIUS_PX * 0.5
+                        }
+                        _ => f32::MAX,
+                    }
+                }
+                Handle::Ring(axis) => polyline_distance(&self.ring(axis)),
+                Handle::Center => {
+                    project(self.center).map_or(f32::MAX, |c| (c - cursor).magnitude() * 0.5)
+                }
+            };
+
+            if distance <= PICK_RADIUS_PX && best.is_none_or(|(_, d)| distance < d) {
+                best = Some((handle, distance));
+            }
+        }
+
+        best.map(|(handle, _)| handle)
+    }
+
+    fn lines(&self, mode: GizmoMode, active: Option<Handle>) -> Vec<GizmoVertex> {
+        let mut lines = Vec::new();
+        let mut line = |a: Point3<f32>, b: Point3<f32>, color: [f32; 4]| {
+            lines.push(GizmoVertex {
+                position: a.into(),
+                color,
+            });
+            lines.push(GizmoVertex {
+                position: b.into(),
+                color,
+            });
+        };
+
+        let color = |handle: Handle, base: [f32; 4]| {
+            if active == Some(handle) {
+                ACTIVE_COLOR
+            } else {
+                base
+            }
+        };
+
+        for handle in Self::handles(mode) {
+            match handle {
+                Handle::Axis(axis) => {
+                    let c = color(handle, AXIS_COLORS[axis]);
+                    let dir = self.axes[axis] * self.size;
+                    let tip = self.center + dir;
+                    line(self.center, tip, c);
+
+                    let (u, v) = plane_axes(axis);
+                    let (u, v) = (self.axes[u] * self.size, self.axes[v] * self.size);
+                    if mode == GizmoMode::Translate {
+                        // Arrow head.
+                        let base = tip - dir * 0.2;
+                        for side in [u, -u, v, -v] {
+                            line(tip, base + side * 0.06, c);
+                        }
+                    } else {
+                        // Scale box.
+                        box_lines(&mut line, tip, (dir * 0.06, u * 0.06, v * 0.06), c);
+                    }
+                }
+                Handle::Plane(normal) => {
+                    let c = color(handle, AXIS_COLORS[normal]);
+                    let corners = self.plane_corners(normal);
+                    for i in 0..4 {
+                        line(corners[i], corners[(i + 1) % 4], c);
+                    }
+                }
+                Handle::Ring(axis) => {
+                    let c = color(handle, AXIS_COLORS[axis]);
+                    for pair in self.ring(axis).windows(2) {
+                        line(pair[0], pair[1], c);
+                    }
+                }
+                Handle::Center => {
+                    let c = color(handle, CENTER_COLOR);
+                    let [x, y, z] = self.axes.map(|axis| axis * self.size * 0.08);
+                    box_lines(&mut line, self.center, (x, y, z), c);
+                }
+            }
+        }
+
+        lines
+    }
+}
+
+fn box_lines(
+    line: &mut impl FnMut(Point3<f32>, Point3<f32>, [f32; 4]),
+    center: Point3<f32>,
+    (x, y, z): (Vector3<f32>, Vector3<f32>, Vector3<f32>),
+    color: [f32; 4],
+) {
+    for (a, b, c) in [(x, y, z), (y, z, x), (z, x, y)] {
+        for (sb, sc) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+            let offset = b * sb + c * sc;
+            line(center - a + offset, center + a + offset, color);
+        }
+    }
+}
+
+fn segment_distance(p: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    let ab = b - a;
+    let length2 = ab.magnitude2();
+    let t = if length2 > 0.0 {
+        ((p - a).dot(ab) / length2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (a + ab * t - p).magnitude()
+}
+
+fn point_in_quad(p: Vector2<f32>, quad: [Vector2<f32>; 4]) -> bool {
+    let side =
+        |a: Vector2<f32>, b: Vector2<f32>| (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    let signs = (0..4).map(|i| side(quad[i], quad[(i + 1) % 4]));
+    let (mut positive, mut negative) = (false, false);
+    for s in signs {
+        positive |= s > 0.0;
+        negative |= s < 0.0;
+    }
+    !(positive && negative)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::offset_of;
+
+    use super::GizmoVertex;
+    use crate::layout;
+    use crate::model::Vertex;
+    use crate::shaders::ShaderId;
+
+    #[test]
+    fn gizmo_vertex_matches_wgsl() {
+        layout::assert_buffer_matches::<GizmoVertex>(
+            &GizmoVertex::desc(),
+            &[
+                (0, offset_of!(GizmoVertex, position)),
+                (1, offset_of!(GizmoVertex, color)),
+            ],
+        );
+        layout::assert_vertex_inputs(ShaderId::Gizmo, "vs_main", &[GizmoVertex::desc()]);
+    }
+}
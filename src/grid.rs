@@ -0,0 +1,197 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    renderer::{create_render_pipeline, PipelineState},
+    shaders::ShaderId,
+    texture,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridSettings {
+    pub enabled: bool,
+    pub spacing: f32,
+    /// Every how many minor lines a major line is drawn.
+    pub major_every: u32,
+    /// Distance from the camera at which the grid has faded out.
+    pub fade_distance: f32,
+    pub show_axes: bool,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            spacing: 1.0,
+            major_every: 10,
+            fade_distance: 60.0,
+            show_axes: true,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct GridUniform {
+    spacing: f32,
+    major_spacing: f32,
+    fade_distance: f32,
+    show_axes: u32,
+}
+
+impl From<GridSettings> for GridUniform {
+    fn from(settings: GridSettings) -> Self {
+        Self {
+            spacing: settings.spacing,
+            major_spacing: settings.spacing * settings.major_every.max(1) as f32,
+            fade_distance: settings.fade_distance,
+            show_axes: settings.show_axes as u32,
+        }
+    }
+}
+
+/// The ground plane grid and world axes, drawn as a fullscreen triangle that
+/// intersects each view ray with `y = 0` and writes the hit's depth.
+pub struct Grid {
+    pub settings: GridSettings,
+    uploaded: Option<GridSettings>,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    color_format: wgpu::TextureFormat,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Grid {
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Grid Buffer"),
+            size: std::mem::size_of::<GridUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("grid_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("grid_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_grid_pipeline(
+            device,
+            &pipeline_layout,
+            color_format,
+            ShaderId::Grid.descriptor(ShaderId::Grid.embedded()),
+        );
+
+        Self {
+            settings: GridSettings::default(),
+            uploaded: None,
+            buffer,
+            bind_group,
+            color_format,
+            pipeline_layout,
+            pipeline,
+        }
+    }
+
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        shader: wgpu::ShaderModuleDescriptor,
+    ) -> wgpu::RenderPipeline {
+        create_grid_pipeline(device, &self.pipeline_layout, self.color_format, shader)
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: wgpu::RenderPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        if self.uploaded != Some(self.settings) {
+            let uniform = GridUniform::from(self.settings);
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+            self.uploaded = Some(self.settings);
+        }
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_grid_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    // Blended over the scene without writing depth, so it never hides
+    // anything drawn after it.
+    create_render_pipeline(
+        device,
+        layout,
+        color_format,
+        &[],
+        PipelineState {
+            cull_mode: None,
+            blend: wgpu::BlendState::ALPHA_BLENDING,
+            depth_format: Some(texture::Texture::DEPTH_FORMAT),
+            depth_write: false,
+            ..Default::default()
+        },
+        shader,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GridUniform;
+    use crate::layout;
+
+    #[test]
+    fn uniform_matches_wgsl() {
+        layout::assert_struct_fields!(
+            GridUniform,
+            "GridSettings",
+            [spacing, major_spacing, fade_distance, show_axes]
+        );
+    }
+}
@@ -0,0 +1,457 @@
+use cgmath::prelude::*;
+use cgmath::{Deg, Matrix3, Matrix4, Quaternion, Vector3};
+
+use bytemuck::{Pod, Zeroable};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Instance {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+    pub color: [f32; 4],
+    pub visible: bool,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+    flags: u32,
+    /// Index in the instance set, which stays the same when culling compacts
+    /// the buffer.
+    id: u32,
+    /// Inverse transpose of `model`, which keeps normals perpendicular under
+    /// non-uniform scale.
+    normal: [[f32; 3]; 3],
+}
+
+impl Instance {
+    pub fn new(position: Vector3<f32>, rotation: Quaternion<f32>) -> Self {
+        Self {
+            position,
+            rotation,
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            color: [1.0, 1.0, 1.0, 1.0],
+            visible: true,
+        }
+    }
+
+    pub fn model_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+
+    /// Whether the tint lets what is behind show through.
+    pub fn is_transparent(&self) -> bool {
+        self.color[3] < 1.0
+    }
+
+    pub fn to_raw(self) -> InstanceRaw {
+        let model = self.model_matrix();
+        InstanceRaw {
+            model: model.into(),
+            color: self.color,
+            flags: 0,
+            id: 0,
+            normal: normal_matrix(&model).into(),
+        }
+    }
+}
+
+/// The inverse transpose of the upper 3x3 of `model`. A degenerate scale
+/// falls back to `model` itself.
+fn normal_matrix(model: &Matrix4<f32>) -> Matrix3<f32> {
+    let linear = Matrix3::from_cols(model.x.truncate(), model.y.truncate(), model.z.truncate());
+    linear
+        .invert()
+        .map_or(linear, |inverse| inverse.transpose())
+}
+
+impl InstanceRaw {
+    pub const FLAG_SELECTED: u32 = 1;
+
+    /// Moves the instance into the space `matrix` places it in.
+    pub fn transformed(mut self, matrix: &Matrix4<f32>) -> Self {
+        let model = matrix * Matrix4::from(self.model);
+        self.model = model.into();
+        self.normal = normal_matrix(&model).into();
+        self
+    }
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 21]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 22]>() as wgpu::BufferAddress,
+                    shader_location: 12,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 25]>() as wgpu::BufferAddress,
+                    shader_location: 13,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 28]>() as wgpu::BufferAddress,
+                    shader_location: 14,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceLayout {
+    Grid,
+    Circle,
+    Spiral,
+    Scatter,
+}
+
+impl InstanceLayout {
+    pub const ALL: [InstanceLayout; 4] = [Self::Grid, Self::Circle, Self::Spiral, Self::Scatter];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Grid => "Grid",
+            Self::Circle => "Circle",
+            Self::Spiral => "Spiral",
+            Self::Scatter => "Random scatter",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationRule {
+    None,
+    /// Tilt each instance around the axis pointing from the origin to it.
+    FacingOut,
+    /// Turn each instance around Y by `index * angle`.
+    Incremental,
+    Random,
+}
+
+impl RotationRule {
+    pub const ALL: [RotationRule; 4] =
+        [Self::None, Self::FacingOut, Self::Incremental, Self::Random];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::FacingOut => "Facing out",
+            Self::Incremental => "Incremental",
+            Self::Random => "Random",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstanceGenerator {
+    pub layout: InstanceLayout,
+    pub count: u32,
+    pub spacing: f32,
+    pub rotation: RotationRule,
+    pub angle: f32,
+    pub scale: f32,
+    pub seed: u64,
+}
+
+impl Default for InstanceGenerator {
+    fn default() -> Self {
+        Self {
+            layout: InstanceLayout::Grid,
+            count: 25,
+            spacing: 3.0,
+            rotation: RotationRule::FacingOut,
+            angle: 45.0,
+            scale: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+impl InstanceGenerator {
+    pub const MAX_COUNT: u32 = 10_000;
+
+    pub fn generate(&self) -> Vec<Instance> {
+        let mut rng = SplitMix64::new(self.seed);
+        let count = self.count.min(Self::MAX_COUNT);
+
+        (0..count)
+            .map(|i| {
+                let position = self.position(i, count, &mut rng);
+                let rotation = self.rotation(i, position, &mut rng);
+
+                Instance {
+                    scale: Vector3::from_value(self.scale),
+                    ..Instance::new(position, rotation)
+                }
+            })
+            .collect()
+    }
+
+    fn position(&self, i: u32, count: u32, rng: &mut SplitMix64) -> Vector3<f32> {
+        match self.layout {
+            InstanceLayout::Grid => {
+                let per_row = (count as f32).sqrt().ceil().max(1.0) as u32;
+                let x = self.spacing * ((i % per_row) as f32 - per_row as f32 / 2.0);
+                let z = self.spacing * ((i / per_row) as f32 - per_row as f32 / 2.0);
+
+                Vector3::new(x, 0.0, z)
+            }
+            InstanceLayout::Circle => {
+                // Keep neighbours `spacing` apart along the circumference.
+                let radius =
+                    (self.spacing * count as f32 / std::f32::consts::TAU).max(self.spacing);
+                let theta = std::f32::consts::TAU * i as f32 / count as f32;
+
+                Vector3::new(radius * theta.cos(), 0.0, radius * theta.sin())
+            }
+            InstanceLayout::Spiral => {
+                // Golden-angle spiral, which spreads points evenly over a disc.
+                const GOLDEN_ANGLE: f32 = 2.399_963;
+                let radius = self.spacing * (i as f32).sqrt();
+                let theta = i as f32 * GOLDEN_ANGLE;
+
+                Vector3::new(radius * theta.cos(), 0.0, radius * theta.sin())
+            }
+            InstanceLayout::Scatter => {
+                let half = self.spacing * (count as f32).sqrt() / 2.0;
+
+                Vector3::new(rng.range(-half, half), 0.0, rng.range(-half, half))
+            }
+        }
+    }
+
+    fn rotation(&self, i: u32, position: Vector3<f32>, rng: &mut SplitMix64) -> Quaternion<f32> {
+        match self.rotation {
+            RotationRule::None => Quaternion::one(),
+            RotationRule::FacingOut => {
+                if position.is_zero() {
+                    Quaternion::one()
+                } else {
+                    Quaternion::from_axis_angle(position.normalize(), Deg(self.angle))
+                }
+            }
+            RotationRule::Incremental => {
+                Quaternion::from_axis_angle(Vector3::unit_y(), Deg(self.angle * i as f32))
+            }
+            RotationRule::Random => {
+                let axis = Vector3::new(
+                    rng.range(-1.0, 1.0),
+                    rng.range(-1.0, 1.0),
+                    rng.range(-1.0, 1.0),
+                );
+
+                if axis.is_zero() {
+                    Quaternion::one()
+                } else {
+                    Quaternion::from_axis_angle(axis.normalize(), Deg(rng.range(0.0, 360.0)))
+                }
+            }
+        }
+    }
+}
+
+/// The instances drawn with the scene model, together with the generator that
+/// produced them. The renderer uploads the visible ones every frame.
+pub struct InstanceSet {
+    pub generator: InstanceGenerator,
+    applied_generator: InstanceGenerator,
+    instances: Vec<Instance>,
+    selected: Option<usize>,
+}
+
+impl InstanceSet {
+    pub fn new(generator: InstanceGenerator) -> Self {
+        Self {
+            generator,
+            applied_generator: generator,
+            instances: generator.generate(),
+            selected: None,
+        }
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index.filter(|&i| i < self.instances.len());
+    }
+
+    /// Removes an instance until the generator settings change again.
+    pub fn remove(&mut self, index: usize) {
+        if index < self.instances.len() {
+            self.instances.remove(index);
+            self.selected = None;
+        }
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Instance> {
+        self.instances.get_mut(index)
+    }
+
+    /// Every visible instance, opaque ones first so they can be drawn as a
+    /// single range, otherwise in order.
+    pub fn visible(&self) -> impl Iterator<Item = (usize, &Instance)> + '_ {
+        let visible = |transparent: bool| {
+            self.instances
+                .iter()
+                .enumerate()
+                .filter(move |(_, instance)| {
+                    instance.visible && instance.is_transparent() == transparent
+                })
+        };
+        visible(false).chain(visible(true))
+    }
+
+    /// Raw data for every visible instance, in the order of `visible`.
+    pub fn visible_raw(&self) -> impl Iterator<Item = InstanceRaw> + '_ {
+        self.visible().map(|(i, instance)| {
+            let mut raw = instance.to_raw();
+            raw.id = i as u32;
+            if self.selected == Some(i) {
+                raw.flags |= InstanceRaw::FLAG_SELECTED;
+            }
+            raw
+        })
+    }
+
+    /// Regenerates the instances if the generator settings changed.
+    pub fn refresh(&mut self) {
+        if self.generator != self.applied_generator {
+            self.instances = self.generator.generate();
+            self.applied_generator = self.generator;
+            self.selected = self.selected.filter(|&i| i < self.instances.len());
+        }
+    }
+}
+
+/// Small deterministic generator so layouts are reproducible from a seed.
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::{offset_of, size_of};
+
+    use cgmath::prelude::*;
+    use cgmath::{Matrix4, Vector3};
+
+    use super::{normal_matrix, InstanceRaw};
+    use crate::layout;
+    use crate::model::{ModelVertex, Vertex};
+    use crate::shaders::ShaderId;
+
+    #[test]
+    fn instance_buffer_matches_wgsl() {
+        let model = offset_of!(InstanceRaw, model);
+        let column = size_of::<[f32; 4]>();
+        let normal = offset_of!(InstanceRaw, normal);
+        let normal_column = size_of::<[f32; 3]>();
+        layout::assert_buffer_matches::<InstanceRaw>(
+            &InstanceRaw::desc(),
+            &[
+                (5, model),
+                (6, model + column),
+                (7, model + 2 * column),
+                (8, model + 3 * column),
+                (9, offset_of!(InstanceRaw, color)),
+                (10, offset_of!(InstanceRaw, flags)),
+                (11, offset_of!(InstanceRaw, id)),
+                (12, normal),
+                (13, normal + normal_column),
+                (14, normal + 2 * normal_column),
+            ],
+        );
+
+        layout::assert_vertex_inputs(
+            ShaderId::Model,
+            "vs_main",
+            &[ModelVertex::desc(), InstanceRaw::desc()],
+        );
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scale() {
+        let model = Matrix4::from_angle_z(cgmath::Deg(30.0))
+            * Matrix4::from_nonuniform_scale(4.0, 1.0, 0.5);
+        // A surface along the diagonal of the unscaled xy plane.
+        let tangent = Vector3::new(1.0, -1.0, 0.0);
+        let normal = Vector3::new(1.0, 1.0, 0.0);
+
+        let world_tangent = model.transform_vector(tangent);
+        let world_normal = normal_matrix(&model) * normal;
+        assert!(world_tangent.dot(world_normal).abs() < 1e-5);
+    }
+}
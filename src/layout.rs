@@ -0,0 +1,254 @@
+//! Checks that the `#[repr(C)]` types shared with the GPU agree with the WGSL
+//! they are read by. Mismatches otherwise only show up as garbage on screen.
+//!
+//! The checks parse the preprocessed shaders embedded by `build.rs` and are
+//! run from the tests next to each type.
+
+use std::collections::HashMap;
+
+use naga::proc::Layouter;
+use naga::{Binding, Module, ScalarKind, ShaderStage, TypeInner};
+
+use crate::shaders::ShaderId;
+
+pub fn parse(shader: ShaderId) -> Module {
+    let source = shader.embedded();
+    naga::front::wgsl::parse_str(source)
+        .unwrap_or_else(|e| panic!("{}: {}", shader.file_name(), e.emit_to_string(source)))
+}
+
+/// Asserts that `T` matches the WGSL struct `name` in every shader declaring
+/// it, and that at least one does. See `assert_struct_fields`.
+pub fn assert_struct_matches<T>(name: &str, fields: &[(&str, usize)]) {
+    let mut declared = false;
+    for shader in ShaderId::ALL {
+        let module = parse(shader);
+        if module
+            .types
+            .iter()
+            .any(|(_, ty)| ty.name.as_deref() == Some(name))
+        {
+            assert_struct_matches_in::<T>(shader, &module, name, fields);
+            declared = true;
+        }
+    }
+    assert!(declared, "no shader declares struct {}", name);
+}
+
+/// Asserts that `T` matches the WGSL struct `name` of every shader declaring
+/// it, pairing each field with the WGSL member of the same name.
+macro_rules! assert_struct_fields {
+    ($ty:ty, $name:expr, [$($field:ident),+ $(,)?]) => {
+        $crate::layout::assert_struct_matches::<$ty>(
+            $name,
+            &[$((stringify!($field), std::mem::offset_of!($ty, $field))),+],
+        )
+    };
+}
+pub(crate) use assert_struct_fields;
+
+/// Asserts that `T` has the same size and field offsets as the WGSL struct
+/// `name` of `shader`, and that every offset satisfies the WGSL member's
+/// alignment. `fields` pairs each WGSL member name with the offset of the
+/// Rust field.
+fn assert_struct_matches_in<T>(
+    shader: ShaderId,
+    module: &Module,
+    name: &str,
+    fields: &[(&str, usize)],
+) {
+    let mut layouter = Layouter::default();
+    layouter.update(&module.types, &module.constants).unwrap();
+
+    let (handle, ty) = module
+        .types
+        .iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some(name))
+        .unwrap_or_else(|| panic!("{}: no struct {}", shader.file_name(), name));
+    let TypeInner::Struct { members, span } = &ty.inner else {
+        panic!("{}: {} is not a struct", shader.file_name(), name);
+    };
+
+    let layout = layouter[handle];
+    assert_eq!(
+        std::mem::size_of::<T>(),
+        *span as usize,
+        "{}: size of {}",
+        shader.file_name(),
+        name
+    );
+    assert!(
+        layout.alignment.is_aligned(*span),
+        "{}: size of {} is not a multiple of its alignment {}",
+        shader.file_name(),
+        name,
+        layout.alignment
+    );
+    assert_eq!(
+        members.len(),
+        fields.len(),
+        "{}: member count of {}",
+        shader.file_name(),
+        name
+    );
+
+    for (member, &(field, offset)) in members.iter().zip(fields) {
+        assert_eq!(
+            member.name.as_deref(),
+            Some(field),
+            "{}: {}",
+            shader.file_name(),
+            name
+        );
+        assert_eq!(
+            member.offset as usize,
+            offset,
+            "{}: offset of {}.{}",
+            shader.file_name(),
+            name,
+            field
+        );
+
+        let alignment = layouter[member.ty].alignment;
+        assert!(
+            alignment.is_aligned(member.offset),
+            "{}: {}.{} is not aligned to {}",
+            shader.file_name(),
+            name,
+            field,
+            alignment
+        );
+    }
+}
+
+/// Asserts that `layout` steps over whole `T`s and that each attribute reads
+/// the Rust field it is meant to. `fields` pairs each `shader_location` with
+/// the offset of its field.
+pub fn assert_buffer_matches<T>(layout: &wgpu::VertexBufferLayout, fields: &[(u32, usize)]) {
+    assert_eq!(
+        layout.array_stride as usize,
+        std::mem::size_of::<T>(),
+        "stride of {}",
+        std::any::type_name::<T>()
+    );
+    assert_eq!(
+        layout.attributes.len(),
+        fields.len(),
+        "attribute count of {}",
+        std::any::type_name::<T>()
+    );
+
+    for (attribute, &(location, offset)) in layout.attributes.iter().zip(fields) {
+        assert_eq!(attribute.shader_location, location);
+        assert_eq!(
+            attribute.offset as usize,
+            offset,
+            "offset of location {} in {}",
+            location,
+            std::any::type_name::<T>()
+        );
+    }
+}
+
+/// Asserts that the vertex buffers in `layouts` provide every `@location` read
+/// by the vertex entry point `entry_point`, with a matching format, and that
+/// each attribute fits inside its buffer's stride.
+pub fn assert_vertex_inputs(
+    shader: ShaderId,
+    entry_point: &str,
+    layouts: &[wgpu::VertexBufferLayout],
+) {
+    let module = parse(shader);
+    let file = shader.file_name();
+
+    let mut attributes = HashMap::new();
+    for layout in layouts {
+        for attribute in layout.attributes {
+            assert!(
+                attribute.offset + attribute.format.size() <= layout.array_stride,
+                "{}: attribute at location {} overruns the stride of {}",
+                file,
+                attribute.shader_location,
+                layout.array_stride
+            );
+            assert!(
+                attributes
+                    .insert(attribute.shader_location, attribute.format)
+                    .is_none(),
+                "{}: location {} is provided twice",
+                file,
+                attribute.shader_location
+            );
+        }
+    }
+
+    let entry = module
+        .entry_points
+        .iter()
+        .find(|entry| entry.stage == ShaderStage::Vertex && entry.name == entry_point)
+        .unwrap_or_else(|| panic!("{}: no vertex entry point {}", file, entry_point));
+
+    let mut inputs = Vec::new();
+    for argument in &entry.function.arguments {
+        match (&argument.binding, &module.types[argument.ty].inner) {
+            (Some(binding), _) => inputs.push((binding, argument.ty)),
+            (None, TypeInner::Struct { members, .. }) => inputs.extend(
+                members
+                    .iter()
+                    .filter_map(|member| Some((member.binding.as_ref()?, member.ty))),
+            ),
+            (None, _) => {}
+        }
+    }
+
+    for (binding, ty) in inputs {
+        let Binding::Location { location, .. } = *binding else {
+            continue;
+        };
+        let format = attributes
+            .get(&location)
+            .unwrap_or_else(|| panic!("{}: nothing provides location {}", file, location));
+
+        assert_eq!(
+            shader_type(&module.types[ty].inner),
+            vertex_format_type(*format),
+            "{}: type of location {} does not match {:?}",
+            file,
+            location,
+            format
+        );
+    }
+}
+
+/// Scalar kind, byte width and component count of a vertex input.
+type ComponentType = Option<(ScalarKind, u8, u8)>;
+
+fn shader_type(inner: &TypeInner) -> ComponentType {
+    match *inner {
+        TypeInner::Scalar { kind, width } => Some((kind, width, 1)),
+        TypeInner::Vector { size, kind, width } => Some((kind, width, size as u8)),
+        _ => None,
+    }
+}
+
+fn vertex_format_type(format: wgpu::VertexFormat) -> ComponentType {
+    use wgpu::VertexFormat as F;
+
+    let (kind, count) = match format {
+        F::Float32 => (ScalarKind::Float, 1),
+        F::Float32x2 => (ScalarKind::Float, 2),
+        F::Float32x3 => (ScalarKind::Float, 3),
+        F::Float32x4 => (ScalarKind::Float, 4),
+        F::Uint32 => (ScalarKind::Uint, 1),
+        F::Uint32x2 => (ScalarKind::Uint, 2),
+        F::Uint32x3 => (ScalarKind::Uint, 3),
+        F::Uint32x4 => (ScalarKind::Uint, 4),
+        F::Sint32 => (ScalarKind::Sint, 1),
+        F::Sint32x2 => (ScalarKind::Sint, 2),
+        F::Sint32x3 => (ScalarKind::Sint, 3),
+        F::Sint32x4 => (ScalarKind::Sint, 4),
+        _ => return None,
+    };
+
+    Some((kind, 4, count))
+}
@@ -0,0 +1,411 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::prelude::*;
+use cgmath::{Deg, Matrix4, Point3, Quaternion, Vector3};
+
+use crate::{camera::Camera, gizmo::GizmoVertex, picking::Ray, scene};
+
+/// Must match `MAX_LIGHTS` in `lights.wgsl`.
+pub const MAX_LIGHTS: usize = 16;
+
+// Like the transform gizmo, the light icons keep a constant size on screen.
+const ICON_SCALE: f32 = 0.03;
+const CIRCLE_SEGMENTS: usize = 32;
+const SELECTED_COLOR: [f32; 4] = [1.0, 0.9, 0.1, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot,
+}
+
+impl LightKind {
+    pub const ALL: [LightKind; 3] = [Self::Directional, Self::Point, Self::Spot];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Directional => "Directional",
+            Self::Point => "Point",
+            Self::Spot => "Spot",
+        }
+    }
+
+    fn id(self) -> u32 {
+        match self {
+            Self::Directional => 0,
+            Self::Point => 1,
+            Self::Spot => 2,
+        }
+    }
+}
+
+/// A light without shadows, on top of the shadow-casting sun. Its node places
+/// it and directional and spot lights shine along the node's -Z axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance at which a point or spot light fades out completely.
+    pub range: f32,
+    /// Half angle of a spot light's cone at full intensity, in degrees.
+    pub inner_angle: f32,
+    /// Half angle of a spot light's cone, beyond which it gives no light.
+    pub outer_angle: f32,
+    pub visible: bool,
+}
+
+impl Light {
+    pub fn new(kind: LightKind) -> Self {
+        Self {
+            kind,
+            color: [1.0; 3],
+            intensity: match kind {
+                LightKind::Directional => 1.0,
+                LightKind::Point | LightKind::Spot => 10.0,
+            },
+            range: 10.0,
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+            visible: true,
+        }
+    }
+
+    /// Where new lights are put: above the origin, shining down and slightly
+    /// forward.
+    pub fn default_transform() -> scene::Transform {
+        let direction = Vector3::new(0.0, -1.0, -0.3).normalize();
+        scene::Transform {
+            translation: Vector3::new(0.0, 2.0, 0.0),
+            rotation: Quaternion::from_arc(-Vector3::unit_z(), direction, None),
+            ..Default::default()
+        }
+    }
+}
+
+/// A light where its node puts it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PlacedLight {
+    light: Light,
+    /// Ignored by directional lights except for drawing their icon.
+    position: Point3<f32>,
+    /// Direction the light travels in, for directional and spot lights.
+    direction: Vector3<f32>,
+}
+
+impl PlacedLight {
+    fn new(light: Light, matrix: &Matrix4<f32>) -> Self {
+        Self {
+            light,
+            position: matrix.transform_point(Point3::origin()),
+            direction: matrix.transform_vector(-Vector3::unit_z()),
+        }
+    }
+
+    fn unit_direction(&self) -> Vector3<f32> {
+        if self.direction.is_zero() {
+            -Vector3::unit_y()
+        } else {
+            self.direction.normalize()
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct LightRaw {
+    position: [f32; 4],
+    direction: [f32; 4],
+    color: [f32; 3],
+    intensity: f32,
+    kind: u32,
+    range: f32,
+    cos_inner: f32,
+    cos_outer: f32,
+}
+
+impl From<&PlacedLight> for LightRaw {
+    fn from(placed: &PlacedLight) -> Self {
+        let light = &placed.light;
+        let cos_outer = Deg(light.outer_angle.clamp(0.0, 90.0)).cos();
+        // Kept apart so the falloff between the two never divides by zero.
+        let cos_inner = Deg(light.inner_angle).cos().max(cos_outer + 1e-4);
+
+        Self {
+            position: placed.position.to_homogeneous().into(),
+            direction: placed.unit_direction().extend(0.0).into(),
+            color: light.color,
+            intensity: light.intensity,
+            kind: light.kind.id(),
+            range: light.range.max(f32::EPSILON),
+            cos_inner,
+            cos_outer,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct LightsUniform {
+    count: u32,
+    _padding0: u32,
+    _padding1: [u32; 2],
+    lights: [LightRaw; MAX_LIGHTS],
+}
+
+impl LightsUniform {
+    fn new(lights: &[PlacedLight]) -> Self {
+        let mut uniform = Self::zeroed();
+        for (raw, light) in uniform.lights.iter_mut().zip(lights) {
+            *raw = light.into();
+        }
+        uniform.count = lights.len().min(MAX_LIGHTS) as u32;
+        uniform
+    }
+}
+
+/// The scene's light list, uploaded to a uniform array of which only the
+/// first `MAX_LIGHTS` are used, and drawn as icons through the gizmo pass.
+/// Each light is placed by the scene node holding its `Component::Light`.
+pub struct Lights {
+    pub lights: Vec<Light>,
+    pub show_gizmos: bool,
+    selected: Option<usize>,
+    /// The world matrix of each light's node, `None` when the node is hidden.
+    placements: Vec<Option<Matrix4<f32>>>,
+    uploaded: Option<Vec<PlacedLight>>,
+    buffer: wgpu::Buffer,
+}
+
+impl Lights {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Lights Buffer"),
+            size: std::mem::size_of::<LightsUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            lights: Vec::new(),
+            show_gizmos: true,
+            selected: None,
+            placements: Vec::new(),
+            uploaded: None,
+            buffer,
+        }
+    }
+
+    /// Places the lights at the world matrices of their nodes, given for the
+    /// visible ones by index. The others are left out.
+    pub fn place(&mut self, placements: impl IntoIterator<Item = (usize, Matrix4<f32>)>) {
+        self.placements = vec![None; self.lights.len()];
+        for (index, matrix) in placements {
+            if let Some(placement) = self.placements.get_mut(index) {
+                *placement = Some(matrix);
+            }
+        }
+    }
+
+    /// The visible lights in world space with their index, leaving out those
+    /// whose node is hidden.
+    fn world_lights(&self) -> Vec<(usize, PlacedLight)> {
+        self.lights
+            .iter()
+            .zip(&self.placements)
+            .enumerate()
+            .filter(|(_, (light, _))| light.visible)
+            .filter_map(|(i, (light, placement))| {
+                placement.map(|matrix| (i, PlacedLight::new(*light, &matrix)))
+            })
+            .collect()
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index.filter(|&i| i < self.lights.len());
+    }
+
+    /// Adds a light and selects it, returning its index, unless the list is
+    /// full. It is unlit until a node places it.
+    pub fn add(&mut self, light: Light) -> Option<usize> {
+        if self.lights.len() == MAX_LIGHTS {
+            return None;
+        }
+        self.lights.push(light);
+        self.placements.push(None);
+        self.selected = Some(self.lights.len() - 1);
+        self.selected
+    }
+
+    /// Moves the later lights down an index, see `Scene::remove_light`.
+    pub fn remove(&mut self, index: usize) {
+        if index < self.lights.len() {
+            self.lights.remove(index);
+            self.placements.remove(index);
+            self.selected = None;
+        }
+    }
+
+    /// The light whose icon the ray hits first, with the distance to it.
+    pub fn pick(&self, ray: &Ray, camera: &Camera) -> Option<(usize, f32)> {
+        if !self.show_gizmos {
+            return None;
+        }
+
+        self.world_lights()
+            .iter()
+            .filter_map(|&(i, placed)| {
+                let radius = icon_size(camera, placed.position);
+                ray.intersect_sphere(placed.position, radius)
+                    .map(|t| (i, t))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Line list drawing every light: a star at its position, an arrow for
+    /// directional lights and a cone for spot lights. The selected light also
+    /// shows its range.
+    pub fn gizmo_lines(&self, camera: &Camera) -> Vec<GizmoVertex> {
+        let mut lines = Vec::new();
+        if !self.show_gizmos {
+            return lines;
+        }
+
+        let mut line = |a: Point3<f32>, b: Point3<f32>, color: [f32; 4]| {
+            lines.push(GizmoVertex {
+                position: a.into(),
+                color,
+            });
+            lines.push(GizmoVertex {
+                position: b.into(),
+                color,
+            });
+        };
+
+        for (i, placed) in self.world_lights() {
+            let light = placed.light;
+            let selected = self.selected == Some(i);
+            let color = if selected {
+                SELECTED_COLOR
+            } else {
+                let [r, g, b] = light.color;
+                [r, g, b, 1.0]
+            };
+            let size = icon_size(camera, placed.position);
+            let center = placed.position;
+
+            for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+                line(center - axis * size, center + axis * size, color);
+            }
+
+            let direction = placed.unit_direction();
+            let (u, v) = perpendicular_axes(direction);
+            match light.kind {
+                LightKind::Directional => {
+                    let tip = center + direction * size * 4.0;
+                    line(center, tip, color);
+                    let base = tip - direction * size;
+                    for side in [u, -u, v, -v] {
+                        line(tip, base + side * size * 0.4, color);
+                    }
+                }
+                LightKind::Point => {
+                    if selected {
+                        for (u, v) in [
+                            (Vector3::unit_x(), Vector3::unit_y()),
+                            (Vector3::unit_y(), Vector3::unit_z()),
+                            (Vector3::unit_z(), Vector3::unit_x()),
+                        ] {
+                            circle(&mut line, center, (u, v), light.range, color);
+                        }
+                    }
+                }
+                LightKind::Spot => {
+                    let length = if selected { light.range } else { size * 4.0 };
+                    let radius = length * Deg(light.outer_angle.clamp(0.0, 89.0)).tan();
+                    let base = center + direction * length;
+                    for side in [u, -u, v, -v] {
+                        line(center, base + side * radius, color);
+                    }
+                    circle(&mut line, base, (u, v), radius, color);
+                }
+            }
+        }
+
+        lines
+    }
+
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        let lights = self
+            .world_lights()
+            .into_iter()
+            .map(|(_, placed)| placed)
+            .collect::<Vec<_>>();
+        if self.uploaded.as_ref() == Some(&lights) {
+            return;
+        }
+
+        let uniform = LightsUniform::new(&lights);
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+        self.uploaded = Some(lights);
+    }
+}
+
+fn icon_size(camera: &Camera, position: Point3<f32>) -> f32 {
+    (camera.eye - position).magnitude() * ICON_SCALE
+}
+
+/// Two unit vectors perpendicular to `direction` and to each other.
+fn perpendicular_axes(direction: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let up = if direction.y.abs() > 0.99 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    };
+    let u = direction.cross(up).normalize();
+    (u, direction.cross(u))
+}
+
+fn circle(
+    line: &mut impl FnMut(Point3<f32>, Point3<f32>, [f32; 4]),
+    center: Point3<f32>,
+    (u, v): (Vector3<f32>, Vector3<f32>),
+    radius: f32,
+    color: [f32; 4],
+) {
+    let point = |i: usize| {
+        let angle = std::f32::consts::TAU * i as f32 / CIRCLE_SEGMENTS as f32;
+        center + (u * angle.cos() + v * angle.sin()) * radius
+    };
+    for i in 0..CIRCLE_SEGMENTS {
+        line(point(i), point(i + 1), color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LightRaw, LightsUniform};
+    use crate::layout;
+
+    #[test]
+    fn uniforms_match_wgsl() {
+        layout::assert_struct_fields!(
+            LightRaw,
+            "PunctualLight",
+            [position, direction, color, intensity, kind, range, cos_inner, cos_outer]
+        );
+        layout::assert_struct_fields!(
+            LightsUniform,
+            "Lights",
+            [count, _padding0, _padding1, lights]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+const CAPACITY: usize = 2000;
+/// Records at or above this level reach the console whatever `RUST_LOG` says.
+const CAPTURE_LEVEL: LevelFilter = LevelFilter::Info;
+
+static RECORDS: Mutex<Records> = Mutex::new(Records {
+    records: VecDeque::new(),
+    pushed: 0,
+});
+
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    /// Since the logger was installed.
+    pub time: Duration,
+}
+
+/// The latest records, oldest first, and how many were ever captured so
+/// readers can copy only the new ones.
+pub struct Records {
+    pub records: VecDeque<LogRecord>,
+    /// Including the dropped ones. Kept by `clear`.
+    pub pushed: u64,
+}
+
+impl Records {
+    /// The records captured after `pushed` was `since`, or as many of them as
+    /// are kept.
+    pub fn since(&self, since: u64) -> impl Iterator<Item = &LogRecord> {
+        let new = (self.pushed - since).min(self.records.len() as u64) as usize;
+        self.records.range(self.records.len() - new..)
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
+
+/// Writes to stderr as `env_logger` would, and keeps the latest records for
+/// the console.
+struct Logger {
+    stderr: env_logger::Logger,
+    start: Instant,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= CAPTURE_LEVEL || self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+        }
+
+        if record.level() > CAPTURE_LEVEL {
+            return;
+        }
+        let mut records = records();
+        if records.records.len() == CAPACITY {
+            records.records.pop_front();
+        }
+        records.records.push_back(LogRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+            time: self.start.elapsed(),
+        });
+        records.pushed += 1;
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+/// Installs the logger, configured for stderr from `RUST_LOG` like
+/// `env_logger::init`.
+pub fn init() {
+    let stderr = env_logger::Builder::from_default_env().build();
+    let max_level = stderr.filter().max(CAPTURE_LEVEL);
+
+    log::set_boxed_logger(Box::new(Logger {
+        stderr,
+        start: Instant::now(),
+    }))
+    .expect("logger already installed");
+    log::set_max_level(max_level);
+}
+
+/// The captured records. Holding them blocks logging, and logging while
+/// holding them deadlocks.
+pub fn records() -> MutexGuard<'static, Records> {
+    // A panic while logging leaves the records usable.
+    RECORDS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    use log::Level;
+
+    use super::{LogRecord, Records};
+
+    fn records(messages: &[&str], pushed: u64) -> Records {
+        Records {
+            records: messages
+                .iter()
+                .map(|message| LogRecord {
+                    level: Level::Info,
+                    target: "test".to_owned(),
+                    message: message.to_string(),
+                    time: Duration::ZERO,
+                })
+                .collect::<VecDeque<_>>(),
+            pushed,
+        }
+    }
+
+    fn messages<'a>(records: impl Iterator<Item = &'a LogRecord>) -> Vec<&'a str> {
+        records.map(|record| record.message.as_str()).collect()
+    }
+
+    #[test]
+    fn since_returns_only_the_new_records() {
+        // Five pushed, the first two dropped.
+        let records = records(&["c", "d", "e"], 5);
+        assert_eq!(messages(records.since(5)), Vec::<&str>::new());
+        assert_eq!(messages(records.since(3)), vec!["d", "e"]);
+        assert_eq!(messages(records.since(0)), vec!["c", "d", "e"]);
+    }
+}
@@ -1,19 +1,43 @@
 mod app;
+mod bloom;
+mod bounds;
+mod buffer;
 mod camera;
 mod core;
 pub mod egui_context;
+mod environment;
 mod event;
+mod frustum;
+mod gizmo;
+mod grid;
+mod instance;
+#[cfg(test)]
+mod layout;
+mod lights;
+mod logger;
 mod model;
 mod mouse;
+mod notifications;
+mod picking;
+mod postprocess;
+mod preprocessor;
+mod profiler;
+mod render_mode;
+mod render_queue;
 mod renderer;
 mod resources;
+mod scene;
+mod shaders;
+mod shadow;
 mod shortcut;
+mod skybox;
 mod texture;
 mod ui;
 pub mod window;
+mod wireframe;
 
 fn main() {
-    env_logger::init();
+    logger::init();
 
     let app = app::App::new().unwrap();
 
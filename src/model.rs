@@ -1,7 +1,12 @@
 use std::ops::Range;
 
-use crate::texture;
+use crate::{
+    bounds::{Aabb, Sphere},
+    environment::Environment,
+    texture,
+};
 use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
 
 pub trait Vertex {
     fn desc() -> wgpu::VertexBufferLayout<'static>;
@@ -71,18 +76,272 @@ impl Vertex for ModelVertex {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    /// Fragments with an alpha below the cutoff are discarded, the others are
+    /// drawn opaque.
+    Mask(f32),
+    /// Blended over what is behind, after every opaque mesh is drawn.
+    Blend,
+}
+
+impl AlphaMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Opaque => "Opaque",
+            Self::Mask(_) => "Mask",
+            Self::Blend => "Blend",
+        }
+    }
+
+    fn cutoff(&self) -> f32 {
+        match self {
+            Self::Mask(cutoff) => *cutoff,
+            Self::Opaque | Self::Blend => 0.0,
+        }
+    }
+}
+
+/// The uniform part of a metallic-roughness material. A factor is only used
+/// when its texture is missing, except for the occlusion strength, which
+/// scales the occlusion texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialFactors {
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    pub occlusion_strength: f32,
+    pub alpha_mode: AlphaMode,
+}
+
+impl Default for MaterialFactors {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: [0.0; 3],
+            occlusion_strength: 1.0,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+}
+
+/// Laid out like glTF: roughness is read from the green channel of
+/// `metallic_roughness` and metallic from its blue channel, occlusion from the
+/// red channel of `occlusion`.
+#[derive(Default)]
+pub struct MaterialTextures {
+    pub base_color: Option<texture::Texture>,
+    pub metallic_roughness: Option<texture::Texture>,
+    pub emissive: Option<texture::Texture>,
+    pub occlusion: Option<texture::Texture>,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct MaterialUniform {
+    base_color: [f32; 4],
+    emissive: [f32; 3],
+    metallic: f32,
+    roughness: f32,
+    occlusion_strength: f32,
+    textures: u32,
+    alpha_cutoff: f32,
+}
+
+impl MaterialUniform {
+    const BASE_COLOR_TEXTURE: u32 = 1 << 0;
+    const METALLIC_ROUGHNESS_TEXTURE: u32 = 1 << 1;
+    const EMISSIVE_TEXTURE: u32 = 1 << 2;
+    const OCCLUSION_TEXTURE: u32 = 1 << 3;
+
+    fn new(factors: &MaterialFactors, textures: &MaterialTextures) -> Self {
+        let flags = [
+            (textures.base_color.is_some(), Self::BASE_COLOR_TEXTURE),
+            (
+                textures.metallic_roughness.is_some(),
+                Self::METALLIC_ROUGHNESS_TEXTURE,
+            ),
+            (textures.emissive.is_some(), Self::EMISSIVE_TEXTURE),
+            (textures.occlusion.is_some(), Self::OCCLUSION_TEXTURE),
+        ];
+
+        Self {
+            base_color: factors.base_color,
+            emissive: factors.emissive,
+            metallic: factors.metallic,
+            roughness: factors.roughness,
+            occlusion_strength: factors.occlusion_strength,
+            textures: flags
+                .iter()
+                .filter(|(present, _)| *present)
+                .fold(0, |acc, (_, flag)| acc | flag),
+            alpha_cutoff: factors.alpha_mode.cutoff(),
+        }
+    }
+}
+
 pub struct Material {
     pub name: String,
-    pub diffuse_texture: texture::Texture,
+    pub factors: MaterialFactors,
+    pub textures: MaterialTextures,
+    uploaded: MaterialFactors,
+    buffer: wgpu::Buffer,
     pub bind_group: wgpu::BindGroup,
 }
 
+impl Material {
+    /// Every binding needs a texture, so missing ones are filled in by this
+    /// white pixel, which the shader never samples. One is shared by all
+    /// materials.
+    pub fn placeholder_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> texture::Texture {
+        texture::Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                1,
+                1,
+                image::Rgba([255; 4]),
+            )),
+            Some("placeholder"),
+        )
+        .unwrap()
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        placeholder: &texture::Texture,
+        name: String,
+        factors: MaterialFactors,
+        textures: MaterialTextures,
+        layout: &wgpu::BindGroupLayout,
+        environment: &Environment,
+    ) -> Self {
+        let uniform = MaterialUniform::new(&factors, &textures);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Material Buffer")),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let [base_color, metallic_roughness, emissive, occlusion] = [
+            &textures.base_color,
+            &textures.metallic_roughness,
+            &textures.emissive,
+            &textures.occlusion,
+        ]
+        .map(|texture| texture.as_ref().unwrap_or(placeholder));
+
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&base_color.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&base_color.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::TextureView(&metallic_roughness.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 8,
+                resource: wgpu::BindingResource::TextureView(&emissive.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 9,
+                resource: wgpu::BindingResource::TextureView(&occlusion.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 10,
+                resource: buffer.as_entire_binding(),
+            },
+        ];
+        entries.extend(environment.bind_group_entries());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some(&name),
+        });
+
+        Self {
+            name,
+            factors,
+            textures,
+            uploaded: factors,
+            buffer,
+            bind_group,
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.factors.alpha_mode == AlphaMode::Blend
+    }
+
+    /// Uploads the factors if they were edited.
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        if self.uploaded != self.factors {
+            let uniform = MaterialUniform::new(&self.factors, &self.textures);
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+            self.uploaded = self.factors;
+        }
+    }
+
+    /// Layout entries for the bindings in `material.wgsl`. The environment
+    /// takes bindings 2 to 6 of the same group.
+    pub fn layout_entries() -> Vec<wgpu::BindGroupLayoutEntry> {
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+
+        vec![
+            texture(0),
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            texture(7),
+            texture(8),
+            texture(9),
+            wgpu::BindGroupLayoutEntry {
+                binding: 10,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ]
+    }
+}
+
 pub struct Mesh {
     pub name: String,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub num_elements: u32,
     pub material: usize,
+    // CPU copies of the geometry, used for picking.
+    pub positions: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+    pub bounds: Aabb,
+    pub sphere: Sphere,
+    pub visible: bool,
 }
 
 pub struct Model {
@@ -106,12 +365,6 @@ pub trait DrawModel<'a> {
     );
 
     fn draw_model(&mut self, model: &'a Model, camera_bind_group: &'a wgpu::BindGroup);
-    fn draw_model_instanced(
-        &mut self,
-        model: &'a Model,
-        instances: Range<u32>,
-        camera_bind_group: &'a wgpu::BindGroup,
-    );
 }
 
 impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
@@ -142,19 +395,56 @@ where
     }
 
     fn draw_model(&mut self, model: &'b Model, camera_bind_group: &'b wgpu::BindGroup) {
-        self.draw_model_instanced(model, 0..1, camera_bind_group);
-    }
-
-    fn draw_model_instanced(
-        &mut self,
-        model: &'b Model,
-        instances: Range<u32>,
-        camera_bind_group: &'b wgpu::BindGroup,
-    ) {
         for mesh in &model.meshes {
-            log::warn!("materials: {}", model.materials.len());
             let material = &model.materials[mesh.material];
-            self.draw_mesh_instanced(mesh, material, instances.clone(), camera_bind_group);
+            self.draw_mesh_instanced(mesh, material, 0..1, camera_bind_group);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::mem::offset_of;
+
+    use super::{MaterialUniform, ModelVertex, PointVertex, Vertex};
+    use crate::layout;
+    use crate::shaders::ShaderId;
+
+    #[test]
+    fn model_vertex_matches_desc() {
+        layout::assert_buffer_matches::<ModelVertex>(
+            &ModelVertex::desc(),
+            &[
+                (0, offset_of!(ModelVertex, position)),
+                (1, offset_of!(ModelVertex, tex_coords)),
+                (2, offset_of!(ModelVertex, normal)),
+            ],
+        );
+    }
+
+    #[test]
+    fn point_vertex_matches_wgsl() {
+        layout::assert_buffer_matches::<PointVertex>(
+            &PointVertex::desc(),
+            &[(3, offset_of!(PointVertex, position))],
+        );
+        layout::assert_vertex_inputs(ShaderId::Point, "vs_main", &[PointVertex::desc()]);
+    }
+
+    #[test]
+    fn material_uniform_matches_wgsl() {
+        layout::assert_struct_fields!(
+            MaterialUniform,
+            "Material",
+            [
+                base_color,
+                emissive,
+                metallic,
+                roughness,
+                occlusion_strength,
+                textures,
+                alpha_cutoff,
+            ]
+        );
+    }
+}
@@ -8,6 +8,7 @@ pub struct Mouse {
     screen: Vector2<f32>,
     pressed: bool,
     released: bool,
+    clicked: bool,
 }
 
 impl Mouse {
@@ -18,6 +19,7 @@ impl Mouse {
             screen: Vector2::new(sw, sh),
             pressed: false,
             released: false,
+            clicked: false,
         }
     }
 
@@ -37,6 +39,19 @@ impl Mouse {
         self.released
     }
 
+    /// Whether the left button went down during the current frame.
+    pub fn clicked(&self) -> bool {
+        self.clicked
+    }
+
+    pub fn resize(&mut self, sw: f32, sh: f32) {
+        self.screen = Vector2::new(sw, sh);
+    }
+
+    pub fn end_frame(&mut self) {
+        self.clicked = false;
+    }
+
     pub fn update_from_viewport(&mut self, x: f32, y: f32) {
         self.pos_viewport = Vector2::new(x, y);
 
@@ -50,6 +65,7 @@ impl Mouse {
         match event {
             WindowEvent::MouseInput { button, state, .. } => {
                 if let MouseButton::Left = button {
+                    self.clicked |= !self.pressed && *state == ElementState::Pressed;
                     self.pressed = *state == ElementState::Pressed;
                     self.released = *state == ElementState::Released;
 
@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::event::AppStatus;
+
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+const MAX_HISTORY: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub status: AppStatus,
+    pub time: Instant,
+}
+
+/// Every status reported so far, the latest ones also shown as toasts until
+/// they expire or are dismissed.
+pub struct Notifications {
+    history: VecDeque<Notification>,
+    toasts: Vec<Notification>,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            toasts: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, status: AppStatus) {
+        let notification = Notification {
+            status,
+            time: Instant::now(),
+        };
+        if self.history.len() == MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(notification.clone());
+        self.toasts.push(notification);
+    }
+
+    /// Drops the toasts older than `TOAST_DURATION`.
+    pub fn expire(&mut self, now: Instant) {
+        self.toasts
+            .retain(|toast| now.duration_since(toast.time) < TOAST_DURATION);
+    }
+
+    pub fn toasts(&self) -> &[Notification] {
+        &self.toasts
+    }
+
+    pub fn dismiss(&mut self, index: usize) {
+        self.toasts.remove(index);
+    }
+
+    /// Oldest first.
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &Notification> {
+        self.history.iter()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+}
@@ -0,0 +1,186 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector2, Vector3, Vector4};
+
+use crate::{bounds::Aabb, camera::Camera, instance::Instance, model::Model};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PickHit {
+    pub instance: usize,
+    pub position: Point3<f32>,
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Ray {
+    /// Builds a world-space ray through the given normalized device coordinate.
+    pub fn from_ndc(camera: &Camera, ndc: Vector2<f32>) -> Option<Self> {
+        let inverse = camera.build_view_projection_matrix().invert()?;
+
+        let unproject = |depth: f32| {
+            let point = inverse * Vector4::new(ndc.x, ndc.y, depth, 1.0);
+            Point3::from_homogeneous(point)
+        };
+
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+
+        Some(Self {
+            origin: near,
+            direction: (far - near).normalize(),
+        })
+    }
+
+    pub fn at(&self, t: f32) -> Point3<f32> {
+        self.origin + self.direction * t
+    }
+
+    /// Transforms the ray without renormalizing, so distances along the
+    /// transformed ray match distances along the original one.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        Self {
+            origin: matrix.transform_point(self.origin),
+            direction: matrix.transform_vector(self.direction),
+        }
+    }
+
+    /// Distance to the first intersection with the sphere, or zero if the
+    /// origin is inside.
+    pub fn intersect_sphere(&self, center: Point3<f32>, radius: f32) -> Option<f32> {
+        let to_center = center - self.origin;
+        let along = to_center.dot(self.direction);
+        let distance2 = to_center.magnitude2() - along * along;
+        let radius2 = radius * radius;
+        if distance2 > radius2 {
+            return None;
+        }
+
+        let half_chord = (radius2 - distance2).sqrt();
+        let (t0, t1) = (along - half_chord, along + half_chord);
+        if t1 < 0.0 {
+            return None;
+        }
+        Some(t0.max(0.0))
+    }
+
+    /// Slab test. Returns the entry distance, or zero if the origin is inside.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut t_min = 0.0f32;
+        let mut t_max = f32::MAX;
+
+        for axis in 0..3 {
+            let inv = 1.0 / self.direction[axis];
+            let mut t0 = (aabb.min[axis] - self.origin[axis]) * inv;
+            let mut t1 = (aabb.max[axis] - self.origin[axis]) * inv;
+            if inv < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max < t_min {
+                return None;
+            }
+        }
+
+        Some(t_min)
+    }
+
+    /// Möller–Trumbore ray/triangle intersection.
+    pub fn intersect_triangle(
+        &self,
+        a: Point3<f32>,
+        b: Point3<f32>,
+        c: Point3<f32>,
+    ) -> Option<f32> {
+        const EPSILON: f32 = 1e-7;
+
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let det = edge1.dot(p);
+        if det.abs() < EPSILON {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let s = self.origin - a;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(q) * inv_det;
+        (t > EPSILON).then_some(t)
+    }
+}
+
+/// Finds the closest visible instance of `model` hit by `ray`, with the
+/// instances placed in `space`.
+pub fn pick_instance(
+    ray: &Ray,
+    model: &Model,
+    space: &Matrix4<f32>,
+    instances: &[Instance],
+) -> Option<PickHit> {
+    let mut closest: Option<PickHit> = None;
+
+    for (index, instance) in instances.iter().enumerate() {
+        if !instance.visible {
+            continue;
+        }
+
+        let model_matrix = space * instance.model_matrix();
+        let Some(inverse) = model_matrix.invert() else {
+            continue;
+        };
+        let local_ray = ray.transformed(&inverse);
+
+        for mesh in model.meshes.iter().filter(|mesh| mesh.visible) {
+            let Some(box_distance) = local_ray.intersect_aabb(&mesh.bounds) else {
+                continue;
+            };
+            if closest.is_some_and(|hit| hit.distance < box_distance) {
+                continue;
+            }
+
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] =
+                    [0, 1, 2].map(|i| Point3::from(mesh.positions[triangle[i] as usize]));
+
+                let Some(distance) = local_ray.intersect_triangle(a, b, c) else {
+                    continue;
+                };
+                if closest.is_some_and(|hit| hit.distance <= distance) {
+                    continue;
+                }
+
+                let local_normal = (b - a).cross(c - a);
+                let normal = inverse
+                    .transpose()
+                    .transform_vector(local_normal)
+                    .normalize();
+
+                closest = Some(PickHit {
+                    instance: index,
+                    position: ray.at(distance),
+                    normal,
+                    distance,
+                });
+            }
+        }
+    }
+
+    closest
+}
@@ -0,0 +1,397 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    bloom::Bloom,
+    renderer::{create_render_pipeline, PipelineState},
+    shaders::ShaderId,
+    texture,
+};
+
+/// Format of the offscreen target the scene is rendered into.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapping {
+    None,
+    Reinhard,
+    Aces,
+}
+
+impl ToneMapping {
+    pub const ALL: [ToneMapping; 3] = [Self::None, Self::Reinhard, Self::Aces];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "None (clamp)",
+            Self::Reinhard => "Reinhard",
+            Self::Aces => "ACES",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostSettings {
+    pub exposure_enabled: bool,
+    /// In stops, so 0 leaves the scene unchanged.
+    pub exposure: f32,
+    pub tone_mapping: ToneMapping,
+    pub gamma_enabled: bool,
+    pub gamma: f32,
+    pub fxaa: bool,
+    pub vignette: bool,
+    pub vignette_strength: f32,
+    /// Distance from the center, as a fraction of the corner distance, where
+    /// the vignette starts.
+    pub vignette_radius: f32,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            exposure_enabled: true,
+            exposure: 0.0,
+            tone_mapping: ToneMapping::Aces,
+            gamma_enabled: true,
+            gamma: 2.2,
+            fxaa: true,
+            vignette: false,
+            vignette_strength: 0.5,
+            vignette_radius: 0.4,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct PostUniform {
+    texel_size: [f32; 2],
+    exposure: f32,
+    tone_mapping: u32,
+    gamma: f32,
+    vignette_strength: f32,
+    vignette_radius: f32,
+    _padding: u32,
+}
+
+impl PostUniform {
+    fn new(settings: &PostSettings, width: u32, height: u32) -> Self {
+        Self {
+            texel_size: [1.0 / width as f32, 1.0 / height as f32],
+            exposure: if settings.exposure_enabled {
+                settings.exposure.exp2()
+            } else {
+                1.0
+            },
+            tone_mapping: settings.tone_mapping as u32,
+            gamma: if settings.gamma_enabled {
+                settings.gamma
+            } else {
+                0.0
+            },
+            vignette_strength: settings.vignette_strength,
+            vignette_radius: settings.vignette_radius,
+            _padding: 0,
+        }
+    }
+}
+
+struct Pass {
+    shader: ShaderId,
+    pipeline: wgpu::RenderPipeline,
+}
+
+/// Owns the HDR scene target and resolves it to the output through a chain of
+/// fullscreen passes: bloom onto the scene, tone mapping (with exposure and
+/// gamma), then the optional FXAA and vignette passes, ping-ponging between
+/// two targets in the output format. The output is expected to be a non-sRGB
+/// view, since the tone mapping pass applies gamma itself.
+pub struct PostProcess {
+    pub settings: PostSettings,
+    pub bloom: Bloom,
+    uploaded: Option<PostUniform>,
+    width: u32,
+    height: u32,
+    output_format: wgpu::TextureFormat,
+    buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    hdr: texture::Texture,
+    hdr_bind_group: wgpu::BindGroup,
+    targets: [(texture::Texture, wgpu::BindGroup); 2],
+    passes: Vec<Pass>,
+}
+
+impl PostProcess {
+    pub const SHADERS: [ShaderId; 3] = [ShaderId::Tonemap, ShaderId::Fxaa, ShaderId::Vignette];
+
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        output_format: wgpu::TextureFormat,
+    ) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Process Buffer"),
+            size: std::mem::size_of::<PostUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post_process_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Process Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let (hdr, hdr_bind_group) = create_target(
+            device,
+            &bind_group_layout,
+            &buffer,
+            width,
+            height,
+            HDR_FORMAT,
+        );
+        let bloom = Bloom::new(device, &hdr, width, height);
+        let targets = [
+            create_target(
+                device,
+                &bind_group_layout,
+                &buffer,
+                width,
+                height,
+                output_format,
+            ),
+            create_target(
+                device,
+                &bind_group_layout,
+                &buffer,
+                width,
+                height,
+                output_format,
+            ),
+        ];
+
+        let mut post = Self {
+            settings: PostSettings::default(),
+            bloom,
+            uploaded: None,
+            width,
+            height,
+            output_format,
+            buffer,
+            bind_group_layout,
+            pipeline_layout,
+            hdr,
+            hdr_bind_group,
+            targets,
+            passes: Vec::new(),
+        };
+
+        post.passes = Self::SHADERS
+            .iter()
+            .map(|&shader| Pass {
+                shader,
+                pipeline: post.create_pipeline(device, shader.descriptor(shader.embedded())),
+            })
+            .collect();
+
+        post
+    }
+
+    /// The target the scene is rendered into.
+    pub fn hdr_view(&self) -> &wgpu::TextureView {
+        &self.hdr.view
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+
+        let layout = &self.bind_group_layout;
+        let buffer = &self.buffer;
+        let format = self.output_format;
+        (self.hdr, self.hdr_bind_group) =
+            create_target(device, layout, buffer, width, height, HDR_FORMAT);
+        self.bloom.resize(device, &self.hdr, width, height);
+        self.targets = [
+            create_target(device, layout, buffer, width, height, format),
+            create_target(device, layout, buffer, width, height, format),
+        ];
+    }
+
+    /// Every pass samples the previous one and writes into an intermediate
+    /// target or the output, which share a format, so any pass can be last.
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        shader: wgpu::ShaderModuleDescriptor,
+    ) -> wgpu::RenderPipeline {
+        create_render_pipeline(
+            device,
+            &self.pipeline_layout,
+            self.output_format,
+            &[],
+            PipelineState {
+                cull_mode: None,
+                ..Default::default()
+            },
+            shader,
+        )
+    }
+
+    pub fn set_pipeline(&mut self, shader: ShaderId, pipeline: wgpu::RenderPipeline) {
+        if let Some(pass) = self.passes.iter_mut().find(|pass| pass.shader == shader) {
+            pass.pipeline = pipeline;
+        }
+    }
+
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        let uniform = PostUniform::new(&self.settings, self.width, self.height);
+        if self.uploaded.as_ref().map(bytemuck::bytes_of) != Some(bytemuck::bytes_of(&uniform)) {
+            queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+            self.uploaded = Some(uniform);
+        }
+
+        self.bloom.prepare(queue);
+    }
+
+    fn enabled(&self, shader: ShaderId) -> bool {
+        match shader {
+            ShaderId::Fxaa => self.settings.fxaa,
+            ShaderId::Vignette => self.settings.vignette,
+            _ => true,
+        }
+    }
+
+    /// Runs the enabled passes, the last one writing into `output`.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        self.bloom.render(encoder, &self.hdr.view);
+
+        let passes = self
+            .passes
+            .iter()
+            .filter(|pass| self.enabled(pass.shader))
+            .collect::<Vec<_>>();
+
+        let mut source = &self.hdr_bind_group;
+        for (i, pass) in passes.iter().enumerate() {
+            let (target, next_source) = if i + 1 == passes.len() {
+                (output, source)
+            } else {
+                let (texture, bind_group) = &self.targets[i % 2];
+                (&texture.view, bind_group)
+            };
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(pass.shader.label()),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(&pass.pipeline);
+            render_pass.set_bind_group(0, source, &[]);
+            render_pass.draw(0..3, 0..1);
+
+            source = next_source;
+        }
+    }
+}
+
+fn create_target(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> (texture::Texture, wgpu::BindGroup) {
+    let label = if format == HDR_FORMAT {
+        "hdr_target"
+    } else {
+        "post_process_target"
+    };
+    let target = texture::Texture::create_render_target(device, width, height, format, label);
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&target.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&target.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    (target, bind_group)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostUniform;
+    use crate::layout;
+
+    #[test]
+    fn uniform_matches_wgsl() {
+        layout::assert_struct_fields!(
+            PostUniform,
+            "PostSettings",
+            [
+                texel_size,
+                exposure,
+                tone_mapping,
+                gamma,
+                vignette_strength,
+                vignette_radius,
+                _padding,
+            ]
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! A small WGSL preprocessor supporting `#include "file.wgsl"`,
+//! `#define NAME [value]`, `#undef`, `#ifdef`, `#ifndef`, `#else` and `#endif`.
+//!
+//! This file is shared with `build.rs`, so it must only depend on `std`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct PreprocessError {
+    pub file: String,
+    pub line: u32,
+    pub message: String,
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+pub struct Preprocessed {
+    pub source: String,
+    /// Every file that contributed to `source`, starting with the entry.
+    pub files: Vec<String>,
+    /// For each output line, the file index and 1-based line it came from.
+    lines: Vec<(usize, u32)>,
+}
+
+impl Preprocessed {
+    /// Maps a 1-based line of the output back to its original file and line.
+    pub fn origin(&self, line: u32) -> Option<(&str, u32)> {
+        let (file, line) = *self.lines.get((line as usize).checked_sub(1)?)?;
+        Some((&self.files[file], line))
+    }
+}
+
+struct Conditional {
+    active: bool,
+    seen_else: bool,
+    line: u32,
+}
+
+struct State<'a> {
+    dir: &'a Path,
+    defines: HashMap<String, String>,
+    stack: Vec<String>,
+    output: Preprocessed,
+}
+
+/// Preprocesses `entry`, resolving includes relative to `dir`. Each file is
+/// included at most once.
+pub fn preprocess(dir: &Path, entry: &str) -> Result<Preprocessed, PreprocessError> {
+    let mut state = State {
+        dir,
+        defines: HashMap::new(),
+        stack: Vec::new(),
+        output: Preprocessed {
+            source: String::new(),
+            files: Vec::new(),
+            lines: Vec::new(),
+        },
+    };
+
+    state.process_file(entry, None)?;
+
+    Ok(state.output)
+}
+
+impl State<'_> {
+    fn process_file(
+        &mut self,
+        name: &str,
+        included_from: Option<(&str, u32)>,
+    ) -> Result<(), PreprocessError> {
+        let error_at_include = |message: String| {
+            let (file, line) = included_from.unwrap_or((name, 0));
+            PreprocessError {
+                file: file.to_owned(),
+                line,
+                message,
+            }
+        };
+
+        if self.stack.iter().any(|file| file == name) {
+            return Err(error_at_include(format!("recursive include of {:?}", name)));
+        }
+        if self.output.files.iter().any(|file| file == name) {
+            return Ok(());
+        }
+
+        let source = std::fs::read_to_string(self.dir.join(name))
+            .map_err(|e| error_at_include(format!("cannot read {:?}: {}", name, e)))?;
+
+        let file_index = self.output.files.len();
+        self.output.files.push(name.to_owned());
+        self.stack.push(name.to_owned());
+
+        let mut conditionals: Vec<Conditional> = Vec::new();
+
+        for (index, text) in source.lines().enumerate() {
+            let line = index as u32 + 1;
+            let error = |message: String| PreprocessError {
+                file: name.to_owned(),
+                line,
+                message,
+            };
+            let active = conditionals.iter().all(|c| c.active);
+
+            let Some(directive) = text.trim_start().strip_prefix('#') else {
+                if active {
+                    let text = self.substitute(text);
+                    self.output.source.push_str(&text);
+                    self.output.source.push('\n');
+                    self.output.lines.push((file_index, line));
+                }
+                continue;
+            };
+
+            let (directive, args) = directive
+                .split_once(char::is_whitespace)
+                .map(|(directive, args)| (directive, args.trim()))
+                .unwrap_or((directive.trim(), ""));
+
+            match directive {
+                "ifdef" | "ifndef" => {
+                    if args.is_empty() {
+                        return Err(error(format!("#{} needs a name", directive)));
+                    }
+                    let defined = self.defines.contains_key(args);
+                    conditionals.push(Conditional {
+                        active: defined == (directive == "ifdef"),
+                        seen_else: false,
+                        line,
+                    });
+                }
+                "else" => {
+                    let conditional = conditionals
+                        .last_mut()
+                        .ok_or_else(|| error("#else without #ifdef".to_owned()))?;
+                    if conditional.seen_else {
+                        return Err(error("duplicate #else".to_owned()));
+                    }
+                    conditional.seen_else = true;
+                    conditional.active = !conditional.active;
+                }
+                "endif" => {
+                    conditionals
+                        .pop()
+                        .ok_or_else(|| error("#endif without #ifdef".to_owned()))?;
+                }
+                "define" | "undef" | "include" if !active => {}
+                "define" => {
+                    let (key, value) = args
+                        .split_once(char::is_whitespace)
+                        .map(|(key, value)| (key, value.trim()))
+                        .unwrap_or((args, ""));
+                    if key.is_empty() {
+                        return Err(error("#define needs a name".to_owned()));
+                    }
+                    self.defines.insert(key.to_owned(), value.to_owned());
+                }
+                "undef" => {
+                    self.defines.remove(args);
+                }
+                "include" => {
+                    let file = args
+                        .strip_prefix('"')
+                        .and_then(|args| args.strip_suffix('"'))
+                        .ok_or_else(|| error("expected #include \"file.wgsl\"".to_owned()))?;
+                    self.process_file(file, Some((name, line)))?;
+                }
+                _ => return Err(error(format!("unknown directive #{}", directive))),
+            }
+        }
+
+        if let Some(conditional) = conditionals.last() {
+            return Err(PreprocessError {
+                file: name.to_owned(),
+                line: conditional.line,
+                message: "unterminated #ifdef".to_owned(),
+            });
+        }
+
+        self.stack.pop();
+
+        Ok(())
+    }
+
+    /// Replaces whole identifiers that have a non-empty `#define` value.
+    fn substitute(&self, text: &str) -> String {
+        if self.defines.values().all(|value| value.is_empty()) {
+            return text.to_owned();
+        }
+
+        let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            // Skip identifiers that start in the middle of a number like `1u`.
+            let preceded_by_ident = rest[..start].chars().last().is_some_and(is_ident);
+            let end = rest[start..]
+                .find(|c: char| !is_ident(c))
+                .map_or(rest.len(), |end| start + end);
+            let word = &rest[start..end];
+
+            result.push_str(&rest[..start]);
+            match self.defines.get(word) {
+                Some(value) if !value.is_empty() && !preceded_by_ident => result.push_str(value),
+                _ => result.push_str(word),
+            }
+            rest = &rest[end..];
+        }
+        result.push_str(rest);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::preprocess;
+
+    /// Writes `files` into a fresh directory named after the test.
+    fn shader_dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("preprocessor-{}-{}", std::process::id(), test));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, source) in files {
+            std::fs::write(dir.join(name), source).unwrap();
+        }
+        dir
+    }
+
+    fn lines(source: &str) -> Vec<&str> {
+        source.lines().collect()
+    }
+
+    #[test]
+    fn includes_are_inlined_once_and_mapped_back() {
+        let dir = shader_dir(
+            "include",
+            &[
+                (
+                    "main.wgsl",
+                    "#include \"a.wgsl\"\n#include \"b.wgsl\"\nmain",
+                ),
+                ("a.wgsl", "#include \"b.wgsl\"\na"),
+                ("b.wgsl", "b"),
+            ],
+        );
+
+        let preprocessed = preprocess(&dir, "main.wgsl").unwrap();
+        assert_eq!(lines(&preprocessed.source), ["b", "a", "main"]);
+        assert_eq!(preprocessed.files, ["main.wgsl", "a.wgsl", "b.wgsl"]);
+        assert_eq!(preprocessed.origin(1), Some(("b.wgsl", 1)));
+        assert_eq!(preprocessed.origin(2), Some(("a.wgsl", 2)));
+        assert_eq!(preprocessed.origin(3), Some(("main.wgsl", 3)));
+        assert_eq!(preprocessed.origin(4), None);
+    }
+
+    #[test]
+    fn include_cycles_are_reported_at_the_include() {
+        let dir = shader_dir(
+            "cycle",
+            &[
+                ("main.wgsl", "#include \"a.wgsl\""),
+                ("a.wgsl", "a\n#include \"b.wgsl\""),
+                ("b.wgsl", "#include \"a.wgsl\""),
+            ],
+        );
+
+        let error = preprocess(&dir, "main.wgsl").err().unwrap();
+        assert_eq!((error.file.as_str(), error.line), ("b.wgsl", 1));
+        assert!(error.message.contains("recursive include"), "{}", error);
+    }
+
+    #[test]
+    fn missing_includes_are_reported_at_the_include() {
+        let dir = shader_dir("missing", &[("main.wgsl", "a\n#include \"none.wgsl\"")]);
+
+        let error = preprocess(&dir, "main.wgsl").err().unwrap();
+        assert_eq!((error.file.as_str(), error.line), ("main.wgsl", 2));
+    }
+
+    #[test]
+    fn nested_conditionals_follow_the_defines() {
+        let source = "\
+#define OUTER
+#ifdef OUTER
+outer
+#ifdef INNER
+inner
+#else
+not_inner
+#ifndef OUTER
+hidden
+#endif
+#endif
+#else
+not_outer
+#endif
+#ifdef INNER
+#define OUTER_ONLY
+#endif
+#ifndef OUTER_ONLY
+end
+#endif";
+        let dir = shader_dir("conditionals", &[("main.wgsl", source)]);
+
+        let preprocessed = preprocess(&dir, "main.wgsl").unwrap();
+        assert_eq!(lines(&preprocessed.source), ["outer", "not_inner", "end"]);
+    }
+
+    #[test]
+    fn unbalanced_conditionals_are_errors() {
+        for (source, line) in [
+            ("#ifdef A\na", 1),
+            ("a\n#endif", 2),
+            ("#else", 1),
+            ("#ifdef A\n#else\n#else\n#endif", 3),
+        ] {
+            let dir = shader_dir("unbalanced", &[("main.wgsl", source)]);
+            let error = preprocess(&dir, "main.wgsl").err().unwrap();
+            assert_eq!(error.line, line, "{:?}: {}", source, error);
+        }
+    }
+
+    #[test]
+    fn defines_replace_whole_identifiers_only() {
+        let source = "\
+#define N 4
+#define FLAG
+array<f32, N> N_MAX MIN_N 1N N.x f(N,N)
+FLAG
+#undef N
+N";
+        let dir = shader_dir("substitute", &[("main.wgsl", source)]);
+
+        let preprocessed = preprocess(&dir, "main.wgsl").unwrap();
+        assert_eq!(
+            lines(&preprocessed.source),
+            ["array<f32, 4> N_MAX MIN_N 1N 4.x f(4,4)", "FLAG", "N"]
+        );
+    }
+}
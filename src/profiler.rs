@@ -0,0 +1,386 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Instant;
+
+/// Frames kept for the graphs and statistics.
+pub const HISTORY: usize = 300;
+/// Timestamps written per frame: the start, then the end of each pass.
+const MAX_TIMESTAMPS: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+}
+
+/// Sample counts in equal ranges of `width` milliseconds from zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub width: f32,
+    /// The start of each range and its count.
+    pub bins: Vec<(f32, usize)>,
+}
+
+/// The durations of one section over the last `HISTORY` frames, in
+/// milliseconds.
+pub struct Timings {
+    pub name: &'static str,
+    samples: VecDeque<f32>,
+}
+
+impl Timings {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            samples: VecDeque::with_capacity(HISTORY),
+        }
+    }
+
+    fn push(&mut self, ms: f32) {
+        if self.samples.len() == HISTORY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ms);
+    }
+
+    /// Oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+
+    pub fn stats(&self) -> Option<Stats> {
+        let count = self.samples.len();
+        if count == 0 {
+            return None;
+        }
+        let (min, max, sum) = self
+            .samples()
+            .fold((f32::MAX, f32::MIN, 0.0), |(min, max, sum), ms| {
+                (min.min(ms), max.max(ms), sum + ms)
+            });
+        Some(Stats {
+            min,
+            avg: sum / count as f32,
+            max,
+        })
+    }
+
+    /// Splits zero to the maximum into `bins` ranges, the last one including
+    /// the maximum. `None` without samples.
+    pub fn histogram(&self, bins: usize) -> Option<Histogram> {
+        let stats = self.stats()?;
+        if bins == 0 {
+            return None;
+        }
+        let width = (stats.max / bins as f32).max(f32::EPSILON);
+        let mut counts = vec![0; bins];
+        for ms in self.samples() {
+            counts[((ms / width) as usize).min(bins - 1)] += 1;
+        }
+        Some(Histogram {
+            width,
+            bins: counts
+                .into_iter()
+                .enumerate()
+                .map(|(i, count)| (i as f32 * width, count))
+                .collect(),
+        })
+    }
+}
+
+/// Records where each frame's time goes: CPU sections measured with
+/// `record`, and GPU passes measured with timestamp queries when the device
+/// supports them.
+pub struct Profiler {
+    /// Keeps the graphs still. Samples are dropped meanwhile.
+    pub paused: bool,
+    frame: Timings,
+    cpu: Vec<Timings>,
+    gpu: Vec<Timings>,
+    current: Vec<(&'static str, f32)>,
+    last_frame: Option<Instant>,
+    timer: Option<GpuTimer>,
+}
+
+impl Profiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let timer = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::new(device, queue.get_timestamp_period()));
+        if timer.is_none() {
+            log::info!("timestamp queries unsupported, GPU passes are not profiled");
+        }
+
+        Self {
+            paused: false,
+            frame: Timings::new("Frame"),
+            cpu: Vec::new(),
+            gpu: Vec::new(),
+            current: Vec::new(),
+            last_frame: None,
+            timer,
+        }
+    }
+
+    /// Adds the time since `start` to the `name` section of this frame.
+    pub fn record(&mut self, name: &'static str, start: Instant) {
+        let ms = start.elapsed().as_secs_f32() * 1000.0;
+        match self
+            .current
+            .iter_mut()
+            .find(|(section, _)| *section == name)
+        {
+            Some((_, total)) => *total += ms,
+            None => self.current.push((name, ms)),
+        }
+    }
+
+    /// Writes the frame's first timestamp. Skipped while the last results
+    /// are still being read back.
+    pub fn begin_gpu(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(timer) = &mut self.timer {
+            timer.begin(encoder);
+        }
+    }
+
+    /// Writes the timestamp ending the `name` pass, which started at the
+    /// previous one.
+    pub fn end_gpu_pass(&mut self, encoder: &mut wgpu::CommandEncoder, name: &'static str) {
+        if let Some(timer) = &mut self.timer {
+            timer.end_pass(encoder, name);
+        }
+    }
+
+    /// Copies the frame's timestamps for reading back once submitted.
+    pub fn resolve_gpu(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(timer) = &mut self.timer {
+            timer.resolve(encoder);
+        }
+    }
+
+    pub fn has_gpu_timings(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    /// Closes the frame. Called after submitting, it also collects the GPU
+    /// durations of an earlier frame if they are ready.
+    pub fn end_frame(&mut self, device: &wgpu::Device) {
+        let now = Instant::now();
+        let frame = self
+            .last_frame
+            .replace(now)
+            .map(|last| now.duration_since(last).as_secs_f32() * 1000.0);
+        let current = std::mem::take(&mut self.current);
+        let gpu = self
+            .timer
+            .as_mut()
+            .and_then(|timer| timer.read(device))
+            .unwrap_or_default();
+
+        if self.paused {
+            return;
+        }
+
+        if let Some(ms) = frame {
+            self.frame.push(ms);
+        }
+        push_all(&mut self.cpu, current);
+        push_all(&mut self.gpu, gpu);
+    }
+
+    /// Time between the ends of two frames.
+    pub fn frame(&self) -> &Timings {
+        &self.frame
+    }
+
+    pub fn cpu(&self) -> &[Timings] {
+        &self.cpu
+    }
+
+    pub fn gpu(&self) -> &[Timings] {
+        &self.gpu
+    }
+}
+
+fn push_all(timings: &mut Vec<Timings>, samples: Vec<(&'static str, f32)>) {
+    for (name, ms) in samples {
+        match timings.iter_mut().find(|t| t.name == name) {
+            Some(t) => t.push(ms),
+            None => {
+                let mut t = Timings::new(name);
+                t.push(ms);
+                timings.push(t);
+            }
+        }
+    }
+}
+
+/// Timestamps resolved into a buffer that is mapped asynchronously, so the
+/// CPU never waits on the GPU. Frames are skipped while a read is pending.
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+    /// The passes ended in the frame being recorded, if it is.
+    passes: Option<Vec<&'static str>>,
+    /// The passes of the frame being read back, and the result of mapping.
+    pending: Option<(Vec<&'static str>, Receiver<bool>)>,
+    resolved: bool,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device, period: f32) -> Self {
+        let size = (MAX_TIMESTAMPS * wgpu::QUERY_SIZE) as u64;
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Profiler Timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_TIMESTAMPS,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Profiler Resolve Buffer"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Profiler Readback Buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            period,
+            passes: None,
+            pending: None,
+            resolved: false,
+        }
+    }
+
+    fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.pending.is_some() || self.resolved {
+            return;
+        }
+        encoder.write_timestamp(&self.query_set, 0);
+        self.passes = Some(Vec::new());
+    }
+
+    fn end_pass(&mut self, encoder: &mut wgpu::CommandEncoder, name: &'static str) {
+        let Some(passes) = &mut self.passes else {
+            return;
+        };
+        let index = passes.len() as u32 + 1;
+        if index < MAX_TIMESTAMPS {
+            encoder.write_timestamp(&self.query_set, index);
+            passes.push(name);
+        }
+    }
+
+    fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(passes) = &self.passes else {
+            return;
+        };
+        let count = passes.len() as u32 + 1;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            (count * wgpu::QUERY_SIZE) as u64,
+        );
+        self.resolved = true;
+    }
+
+    /// Starts mapping the timestamps submitted this frame, and returns the
+    /// pass durations of the last mapping to complete.
+    fn read(&mut self, device: &wgpu::Device) -> Option<Vec<(&'static str, f32)>> {
+        if self.resolved {
+            self.resolved = false;
+            let passes = self.passes.take().unwrap_or_default();
+            let (sender, receiver) = mpsc::channel();
+            self.readback_buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let _ = sender.send(result.is_ok());
+                });
+            self.pending = Some((passes, receiver));
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        let (_, receiver) = self.pending.as_ref()?;
+        match receiver.try_recv() {
+            Err(TryRecvError::Empty) => return None,
+            Ok(true) => {}
+            Ok(false) | Err(TryRecvError::Disconnected) => {
+                self.pending = None;
+                return None;
+            }
+        }
+
+        let (passes, _) = self.pending.take()?;
+        let durations = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            passes
+                .iter()
+                .enumerate()
+                .map(|(i, &name)| {
+                    let ticks = timestamps[i + 1].saturating_sub(timestamps[i]);
+                    (name, ticks as f32 * self.period / 1_000_000.0)
+                })
+                .collect()
+        };
+        self.readback_buffer.unmap();
+
+        Some(durations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Stats, Timings, HISTORY};
+
+    fn timings(samples: &[f32]) -> Timings {
+        let mut timings = Timings::new("test");
+        for &ms in samples {
+            timings.push(ms);
+        }
+        timings
+    }
+
+    #[test]
+    fn stats_cover_the_kept_samples() {
+        assert_eq!(timings(&[]).stats(), None);
+        assert_eq!(
+            timings(&[2.0, 1.0, 6.0]).stats(),
+            Some(Stats {
+                min: 1.0,
+                avg: 3.0,
+                max: 6.0,
+            })
+        );
+
+        // The oldest samples are dropped past `HISTORY`.
+        let mut samples = vec![100.0];
+        samples.extend(std::iter::repeat_n(1.0, HISTORY));
+        let stats = timings(&samples).stats().unwrap();
+        assert_eq!(stats.max, 1.0);
+    }
+
+    #[test]
+    fn histogram_counts_every_sample_once() {
+        assert_eq!(timings(&[]).histogram(4), None);
+        assert_eq!(timings(&[1.0]).histogram(0), None);
+
+        let histogram = timings(&[0.0, 0.5, 1.0, 2.5, 4.0, 4.0])
+            .histogram(4)
+            .unwrap();
+        assert_eq!(histogram.width, 1.0);
+        // The maximum falls in the last bin rather than past it.
+        assert_eq!(histogram.bins, vec![(0.0, 2), (1.0, 1), (2.0, 1), (3.0, 2)]);
+    }
+}
@@ -0,0 +1,186 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::camera::Camera;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    Shaded,
+    Unlit,
+    Wireframe,
+    Normals,
+    UvChecker,
+    Depth,
+    MeshColors,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 7] = [
+        Self::Shaded,
+        Self::Unlit,
+        Self::Wireframe,
+        Self::Normals,
+        Self::UvChecker,
+        Self::Depth,
+        Self::MeshColors,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Shaded => "Shaded",
+            Self::Unlit => "Unlit albedo",
+            Self::Wireframe => "Wireframe overlay",
+            Self::Normals => "World normals",
+            Self::UvChecker => "UV checker",
+            Self::Depth => "Depth",
+            Self::MeshColors => "Random mesh colors",
+        }
+    }
+
+    /// The value of `RenderSettings::mode` in `render_mode.wgsl`. The
+    /// wireframe overlay is drawn on top of the shaded scene.
+    fn shader_mode(&self) -> u32 {
+        match self {
+            Self::Shaded | Self::Wireframe => 0,
+            Self::Unlit => 1,
+            Self::Normals => 2,
+            Self::UvChecker => 3,
+            Self::Depth => 4,
+            Self::MeshColors => 5,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct RenderSettingsUniform {
+    mode: u32,
+    mesh: u32,
+    znear: f32,
+    zfar: f32,
+}
+
+/// One uniform slot per mesh, selected with a dynamic offset, so the mesh
+/// colors mode can tell meshes apart.
+pub struct RenderSettings {
+    pub mode: RenderMode,
+    buffer: wgpu::Buffer,
+    slot_size: wgpu::BufferAddress,
+    mesh_count: usize,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl RenderSettings {
+    pub fn new(device: &wgpu::Device, mesh_count: usize) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("render_settings_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<RenderSettingsUniform>() as u64,
+                    ),
+                },
+                count: None,
+            }],
+        });
+
+        let slot_size =
+            wgpu::BufferAddress::from(device.limits().min_uniform_buffer_offset_alignment)
+                .max(std::mem::size_of::<RenderSettingsUniform>() as u64);
+        let mesh_count = mesh_count.max(1);
+        let buffer = create_buffer(device, slot_size * mesh_count as u64);
+        let bind_group = create_bind_group(device, &bind_group_layout, &buffer);
+
+        Self {
+            mode: RenderMode::Shaded,
+            buffer,
+            slot_size,
+            mesh_count,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Grows the buffer to a slot for each of `mesh_count` meshes, the slots
+    /// being indexed by the mesh's position in its model.
+    pub fn reserve(&mut self, device: &wgpu::Device, mesh_count: usize) {
+        if mesh_count <= self.mesh_count {
+            return;
+        }
+        self.mesh_count = mesh_count;
+        self.buffer = create_buffer(device, self.slot_size * mesh_count as u64);
+        self.bind_group = create_bind_group(device, &self.bind_group_layout, &self.buffer);
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        for mesh in 0..self.mesh_count {
+            let uniform = RenderSettingsUniform {
+                mode: self.mode.shader_mode(),
+                mesh: mesh as u32,
+                znear: camera.znear,
+                zfar: camera.zfar,
+            };
+            queue.write_buffer(
+                &self.buffer,
+                self.slot_size * mesh as u64,
+                bytemuck::cast_slice(&[uniform]),
+            );
+        }
+    }
+
+    pub fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, index: u32, mesh: usize) {
+        let offset = self.slot_size * mesh as u64;
+        render_pass.set_bind_group(index, &self.bind_group, &[offset as u32]);
+    }
+}
+
+fn create_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Render Settings Buffer"),
+        size,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("render_settings_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(std::mem::size_of::<RenderSettingsUniform>() as u64),
+            }),
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderSettingsUniform;
+    use crate::layout;
+
+    #[test]
+    fn uniform_matches_wgsl() {
+        layout::assert_struct_fields!(
+            RenderSettingsUniform,
+            "RenderSettings",
+            [mode, mesh, znear, zfar]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Quaternion, Vector3};
+
+use crate::{
+    camera::Camera,
+    frustum::Frustum,
+    instance::{Instance, InstanceRaw, InstanceSet},
+    scene::{Component, Scene},
+};
+
+/// One mesh of `Scene::models` drawn for a range of the instance buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Draw {
+    pub model: usize,
+    pub mesh: usize,
+    pub instances: Range<u32>,
+}
+
+/// Mesh instances kept and dropped by the last `update`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CullingStats {
+    pub visible: usize,
+    pub culled: usize,
+    /// Outside the light's frustum, so left out of the shadow map.
+    pub casters_culled: usize,
+}
+
+/// Splits the draws of the scene's model nodes in two. Opaque and
+/// alpha-tested meshes are drawn first, one instanced draw each over their
+/// opaque instances. Everything that blends, either through its material or
+/// its instance tint, follows one instance at a time, sorted back-to-front.
+///
+/// Each mesh instance outside the camera frustum is dropped, so the instance
+/// data is rebuilt every frame, compacted per draw and already multiplied by
+/// the node's world matrix.
+pub struct RenderQueues {
+    pub opaque: Vec<Draw>,
+    pub transparent: Vec<Draw>,
+    /// The mesh instances inside the light's frustum, for the shadow pass.
+    pub shadow_casters: Vec<Draw>,
+    pub culling: bool,
+    pub stats: CullingStats,
+    instances: Vec<InstanceRaw>,
+}
+
+impl Default for RenderQueues {
+    fn default() -> Self {
+        Self {
+            opaque: Vec::new(),
+            transparent: Vec::new(),
+            shadow_casters: Vec::new(),
+            culling: true,
+            stats: CullingStats::default(),
+            instances: Vec::new(),
+        }
+    }
+}
+
+impl RenderQueues {
+    /// Instance data the draws' ranges index into.
+    pub fn instances(&self) -> &[InstanceRaw] {
+        &self.instances
+    }
+
+    pub fn update(
+        &mut self,
+        camera: &Camera,
+        light_view_proj: &Matrix4<f32>,
+        scene: &Scene,
+        instances: &InstanceSet,
+    ) {
+        self.opaque.clear();
+        self.transparent.clear();
+        self.shadow_casters.clear();
+        self.instances.clear();
+        self.stats = CullingStats::default();
+
+        let frustum = Frustum::from_view_proj(&camera.build_view_projection_matrix());
+        let light_frustum = Frustum::from_view_proj(light_view_proj);
+        // Whether each placement is tinted transparent, its matrix and its
+        // instance data, relative to the model's node.
+        let instanced = instances
+            .visible()
+            .zip(instances.visible_raw())
+            .map(|((_, instance), raw)| (instance.is_transparent(), instance.model_matrix(), raw))
+            .collect::<Vec<_>>();
+        let origin = Instance::new(Vector3::zero(), Quaternion::one());
+        let single = [(false, Matrix4::identity(), origin.to_raw())];
+
+        let mut sorted = Vec::new();
+        for (node, component) in scene.components() {
+            let Component::Model {
+                model,
+                instanced: is_instanced,
+            } = component
+            else {
+                continue;
+            };
+
+            let world = scene.world(node);
+            let placements = if is_instanced {
+                &instanced[..]
+            } else {
+                &single[..]
+            };
+            let placements = placements
+                .iter()
+                .map(|(transparent, matrix, raw)| {
+                    (*transparent, world * matrix, raw.transformed(&world))
+                })
+                .collect::<Vec<_>>();
+
+            for (m, mesh) in scene.models[model].meshes.iter().enumerate() {
+                if !mesh.visible {
+                    continue;
+                }
+                let transparent_mesh =
+                    scene.models[model].materials[mesh.material].is_transparent();
+
+                // Shadows may fall from outside the view, so casters are
+                // culled against the light's frustum instead.
+                let start = self.instances.len() as u32;
+                for (_, matrix, raw) in &placements {
+                    if self.culling
+                        && !light_frustum.contains_mesh(&mesh.sphere, &mesh.bounds, matrix)
+                    {
+                        self.stats.casters_culled += 1;
+                        continue;
+                    }
+                    self.instances.push(*raw);
+                }
+                let end = self.instances.len() as u32;
+                if start < end {
+                    self.shadow_casters.push(Draw {
+                        model,
+                        mesh: m,
+                        instances: start..end,
+                    });
+                }
+
+                let start = self.instances.len() as u32;
+                for (transparent, matrix, raw) in &placements {
+                    if self.culling && !frustum.contains_mesh(&mesh.sphere, &mesh.bounds, matrix) {
+                        self.stats.culled += 1;
+                        continue;
+                    }
+                    self.stats.visible += 1;
+
+                    if transparent_mesh || *transparent {
+                        let center = matrix.transform_point(mesh.bounds.center());
+                        sorted.push((camera.eye.distance2(center), model, m, *raw));
+                    } else {
+                        self.instances.push(*raw);
+                    }
+                }
+
+                let end = self.instances.len() as u32;
+                if start < end {
+                    self.opaque.push(Draw {
+                        model,
+                        mesh: m,
+                        instances: start..end,
+                    });
+                }
+            }
+        }
+
+        sorted.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, model, mesh, raw) in sorted {
+            let index = self.instances.len() as u32;
+            self.instances.push(raw);
+            self.transparent.push(Draw {
+                model,
+                mesh,
+                instances: index..index + 1,
+            });
+        }
+    }
+}
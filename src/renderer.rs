@@ -1,18 +1,35 @@
+use std::time::Instant;
+
 use cgmath::prelude::*;
-use cgmath::Vector2;
 use cgmath::Vector3;
+use cgmath::{Matrix4, Vector2};
 use egui::{ClippedPrimitive, TexturesDelta};
 use egui_wgpu::renderer::ScreenDescriptor;
-use wgpu::include_wgsl;
 use wgpu::util::DeviceExt;
 use winit::{event::WindowEvent, window::Window};
 
-use bytemuck::{Pod, Zeroable};
-
+use crate::buffer::DynamicBuffer;
+use crate::environment::Environment;
+use crate::event::AppStatus;
+use crate::gizmo::Gizmo;
+use crate::grid::Grid;
+use crate::instance::{InstanceGenerator, InstanceRaw, InstanceSet};
+use crate::lights::{Light, LightKind, Lights};
 use crate::model;
 use crate::model::PointVertex;
 use crate::model::{DrawModel, Vertex};
 use crate::mouse::Mouse;
+use crate::picking::{self, PickHit, Ray};
+use crate::postprocess::{PostProcess, HDR_FORMAT};
+use crate::preprocessor::Preprocessed;
+use crate::profiler::Profiler;
+use crate::render_mode::{RenderMode, RenderSettings};
+use crate::render_queue::{Draw, RenderQueues};
+use crate::scene::{Component, NodeId, Scene, Transform};
+use crate::shaders::{self, ShaderError, ShaderId, ShaderWatcher};
+use crate::shadow::Shadows;
+use crate::skybox::Skybox;
+use crate::wireframe::Wireframe;
 use crate::{
     camera::{Camera, CameraController, CameraUniform},
     texture,
@@ -20,61 +37,6 @@ use crate::{
 
 const INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
 
-const NUM_INSTANCES_PER_ROW: u32 = 5;
-
-pub struct Instance {
-    position: cgmath::Vector3<f32>,
-    rotation: cgmath::Quaternion<f32>,
-}
-
-#[repr(C)]
-#[derive(Copy, Clone, Pod, Zeroable)]
-pub struct InstanceRaw {
-    model: [[f32; 4]; 4],
-}
-
-impl Instance {
-    fn to_raw(&self) -> InstanceRaw {
-        InstanceRaw {
-            model: (cgmath::Matrix4::from_translation(self.position)
-                * cgmath::Matrix4::from(self.rotation))
-            .into(),
-        }
-    }
-}
-
-impl InstanceRaw {
-    fn desc() -> wgpu::VertexBufferLayout<'static> {
-        use std::mem;
-        wgpu::VertexBufferLayout {
-            array_stride: mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Instance,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 5,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
-                    shader_location: 6,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                    shader_location: 7,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
-                    shader_location: 8,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-            ],
-        }
-    }
-}
-
 pub struct Renderer {
     #[allow(dead_code)]
     instance: wgpu::Instance,
@@ -84,25 +46,42 @@ pub struct Renderer {
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
+    /// The non-sRGB view of the surface the post-processing chain and the
+    /// overlays write to.
+    output_format: wgpu::TextureFormat,
     pub size: winit::dpi::PhysicalSize<u32>,
-    clear_color: wgpu::Color,
     index_buffer: wgpu::Buffer,
     num_indices: u32,
+    render_pipeline_layout: wgpu::PipelineLayout,
     render_pipeline: wgpu::RenderPipeline,
-    diffuse_texture: texture::Texture,
-    diffuse_bind_group: wgpu::BindGroup,
+    transparent_pipeline: wgpu::RenderPipeline,
+    pub queues: RenderQueues,
 
+    point_pipeline_layout: wgpu::PipelineLayout,
     point_render_pipeline: wgpu::RenderPipeline,
+    pub shaders: ShaderWatcher,
 
     depth_texture: texture::Texture,
 
-    vertex_buffer: wgpu::Buffer,
-    points: Vec<PointVertex>,
-
-    instances: Vec<Instance>,
-    instance_buffer: wgpu::Buffer,
+    point_buffer: DynamicBuffer<PointVertex>,
+
+    pub instances: InstanceSet,
+    // Rebuilt by the render queues every frame.
+    instance_buffer: DynamicBuffer<InstanceRaw>,
+    last_pick: Option<PickHit>,
+    pub gizmo: Gizmo,
+    pub grid: Grid,
+    pub post: PostProcess,
+    pub lights: Lights,
+    pub shadows: Shadows,
+    pub skybox: Skybox,
+    pub environment: Environment,
+    pub render_settings: RenderSettings,
+    pub wireframe: Wireframe,
 
     pub camera: Camera,
+    /// `camera` placed by its scene node, which everything renders from.
+    view_camera: Camera,
     mouse: Mouse,
     camera_uniform: CameraUniform,
     camera_buffer: wgpu::Buffer,
@@ -111,16 +90,46 @@ pub struct Renderer {
     camera_controller: CameraController,
 
     egui_renderer: egui_wgpu::renderer::Renderer,
-    obj_model: crate::model::Model,
+    pub scene: Scene,
+    /// Reported since the last `take_statuses`.
+    statuses: Vec<AppStatus>,
+    pub profiler: Profiler,
+}
+
+/// The fixed-function state that differs between the pipelines built with
+/// `create_render_pipeline`. The default draws opaque, back-face culled
+/// triangles without a depth buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineState {
+    pub topology: wgpu::PrimitiveTopology,
+    pub polygon_mode: wgpu::PolygonMode,
+    pub cull_mode: Option<wgpu::Face>,
+    pub blend: wgpu::BlendState,
+    pub depth_format: Option<wgpu::TextureFormat>,
+    pub depth_write: bool,
+    pub depth_compare: wgpu::CompareFunction,
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        Self {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            cull_mode: Some(wgpu::Face::Back),
+            blend: wgpu::BlendState::REPLACE,
+            depth_format: None,
+            depth_write: true,
+            depth_compare: wgpu::CompareFunction::Less,
+        }
+    }
 }
 
 pub fn create_render_pipeline(
     device: &wgpu::Device,
     layout: &wgpu::PipelineLayout,
-    primitive_topology: wgpu::PrimitiveTopology,
     color_format: wgpu::TextureFormat,
-    depth_format: Option<wgpu::TextureFormat>,
     vertex_layouts: &[wgpu::VertexBufferLayout],
+    state: PipelineState,
     shader: wgpu::ShaderModuleDescriptor,
 ) -> wgpu::RenderPipeline {
     let shader = device.create_shader_module(shader);
@@ -138,29 +147,26 @@ pub fn create_render_pipeline(
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: color_format,
-                blend: Some(wgpu::BlendState {
-                    alpha: wgpu::BlendComponent::REPLACE,
-                    color: wgpu::BlendComponent::REPLACE,
-                }),
+                blend: Some(state.blend),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
         primitive: wgpu::PrimitiveState {
-            topology: primitive_topology,
+            topology: state.topology,
             strip_index_format: None,
             front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-            polygon_mode: wgpu::PolygonMode::Fill,
+            cull_mode: state.cull_mode,
+            // Line requires Features::POLYGON_MODE_LINE, Point requires POLYGON_MODE_POINT
+            polygon_mode: state.polygon_mode,
             // Requires Features::DEPTH_CLIP_CONTROL
             unclipped_depth: false,
             // Requires Features::CONSERVATIVE_RASTERIZATION
             conservative: false,
         },
-        depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+        depth_stencil: state.depth_format.map(|format| wgpu::DepthStencilState {
             format,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
+            depth_write_enabled: state.depth_write,
+            depth_compare: state.depth_compare,
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
@@ -173,6 +179,56 @@ pub fn create_render_pipeline(
     })
 }
 
+/// The transparent variant blends and leaves the depth buffer alone, so
+/// everything behind stays visible whatever the draw order.
+fn create_model_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    transparent: bool,
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    let (blend, depth_write) = if transparent {
+        (wgpu::BlendState::ALPHA_BLENDING, false)
+    } else {
+        (wgpu::BlendState::REPLACE, true)
+    };
+
+    create_render_pipeline(
+        device,
+        layout,
+        color_format,
+        &[model::ModelVertex::desc(), InstanceRaw::desc()],
+        PipelineState {
+            blend,
+            depth_format: Some(texture::Texture::DEPTH_FORMAT),
+            depth_write,
+            ..Default::default()
+        },
+        shader,
+    )
+}
+
+fn create_point_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    create_render_pipeline(
+        device,
+        layout,
+        color_format,
+        &[model::PointVertex::desc()],
+        PipelineState {
+            topology: wgpu::PrimitiveTopology::PointList,
+            depth_format: Some(texture::Texture::DEPTH_FORMAT),
+            ..Default::default()
+        },
+        shader,
+    )
+}
+
 impl Renderer {
     pub async fn new(window: &Window) -> Self {
         let size = window.inner_size();
@@ -201,7 +257,10 @@ impl Renderer {
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
+                    // Optional, the wireframe falls back to a shader without
+                    // line mode and the profiler skips the GPU passes.
+                    features: adapter.features()
+                        & (wgpu::Features::POLYGON_MODE_LINE | wgpu::Features::TIMESTAMP_QUERY),
                     limits: wgpu::Limits::default(),
                     label: None,
                 },
@@ -219,6 +278,10 @@ impl Renderer {
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
 
+        // Tone mapping encodes gamma itself, so the surface is written
+        // through a linear view.
+        let output_format = surface_format.remove_srgb_suffix();
+
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
@@ -226,53 +289,24 @@ impl Renderer {
             height: size.height,
             present_mode: surface_caps.present_modes[0],
             alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
+            view_formats: if output_format != surface_format {
+                vec![output_format]
+            } else {
+                vec![]
+            },
         };
 
         surface.configure(&device, &config);
 
-        let diffuse_bytes = include_bytes!("cat.png");
-        let diffuse_texture =
-            texture::Texture::from_bytes(&device, &queue, diffuse_bytes, "cat.png").unwrap();
-
+        // Materials also carry the environment maps, see `environment.wgsl`.
+        let mut texture_layout_entries = model::Material::layout_entries();
+        texture_layout_entries.extend(Environment::layout_entries());
         let texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
+                entries: &texture_layout_entries,
                 label: Some("texture_bind_group_layout"),
             });
 
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
-
         let camera = Camera {
             eye: (0.0, 1.0, 2.0).into(),
             target: (0.0, 0.0, 0.0).into(),
@@ -318,86 +352,85 @@ impl Renderer {
 
         let camera_controller = CameraController::new(0.2);
 
-        let clear_color = wgpu::Color::BLACK;
-
-        const SPACE_BETWEEN: f32 = 3.0;
-        let instances = (0..NUM_INSTANCES_PER_ROW)
-            .flat_map(|z| {
-                (0..NUM_INSTANCES_PER_ROW).map(move |x| {
-                    let x = SPACE_BETWEEN * (x as f32 - NUM_INSTANCES_PER_ROW as f32 / 2.0);
-                    let z = SPACE_BETWEEN * (z as f32 - NUM_INSTANCES_PER_ROW as f32 / 2.0);
-
-                    let position = cgmath::Vector3 { x, y: 0.0, z };
-
-                    let rotation = if position.is_zero() {
-                        cgmath::Quaternion::from_axis_angle(
-                            cgmath::Vector3::unit_z(),
-                            cgmath::Deg(0.0),
-                        )
-                    } else {
-                        cgmath::Quaternion::from_axis_angle(position.normalize(), cgmath::Deg(45.0))
-                    };
-
-                    Instance { position, rotation }
-                })
-            })
-            .collect::<Vec<_>>();
-
-        let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Instance Buffer"),
-            contents: bytemuck::cast_slice(&instance_data),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let mut skybox = Skybox::new(&device, &queue, HDR_FORMAT, &camera_bind_group_layout);
+        let sky_status = skybox.load(&device, &queue).await;
+        let environment = Environment::new(&device, &queue, skybox.cubemap());
+
+        let profiler = Profiler::new(&device, &queue);
+
+        let placeholder_texture = model::Material::placeholder_texture(&device, &queue);
+        let (cube, warnings) = crate::resources::load_model(
+            "cube.obj",
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            &environment,
+            &placeholder_texture,
+        )
+        .await
+        .unwrap();
+
+        let gizmo = Gizmo::new(&device, output_format, &camera_bind_group_layout);
+        let grid = Grid::new(&device, HDR_FORMAT, &camera_bind_group_layout);
+        let wireframe = Wireframe::new(&device, HDR_FORMAT, &camera_bind_group_layout);
+        let render_settings = RenderSettings::new(&device, cube.meshes.len());
+        let lights = Lights::new(&device);
+        let shadows = Shadows::new(&device, &lights);
+        let post = PostProcess::new(&device, config.width, config.height, output_format);
+
+        let mut scene = Scene::new();
+        scene.add_node("Camera", None, vec![Component::Camera]);
+        scene.add_node("Points", None, vec![Component::Points]);
+
+        let instances = InstanceSet::new(InstanceGenerator::default());
+        let instance_buffer =
+            DynamicBuffer::new(&device, "Instance Buffer", wgpu::BufferUsages::VERTEX);
 
         let depth_texture =
             texture::Texture::create_depth_texture(&device, &config, "depth_texture");
 
-        let render_pipeline = {
-            let shader = wgpu::ShaderModuleDescriptor {
-                label: Some("Normal Shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-            };
-
-            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                bind_group_layouts: &[&texture_bind_group_layout, &camera_bind_group_layout],
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                bind_group_layouts: &[
+                    &texture_bind_group_layout,
+                    &camera_bind_group_layout,
+                    render_settings.bind_group_layout(),
+                    shadows.lighting_bind_group_layout(),
+                ],
                 label: Some("render_pipeline_layout"),
                 push_constant_ranges: &[],
             });
 
-            create_render_pipeline(
-                &device,
-                &layout,
-                wgpu::PrimitiveTopology::TriangleList,
-                config.format,
-                Some(texture::Texture::DEPTH_FORMAT),
-                &[model::ModelVertex::desc(), InstanceRaw::desc()],
-                shader,
-            )
-        };
-
-        let point_render_pipeline = {
-            let shader = wgpu::ShaderModuleDescriptor {
-                label: Some("Point Shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("point.wgsl").into()),
-            };
+        let render_pipeline = create_model_pipeline(
+            &device,
+            &render_pipeline_layout,
+            HDR_FORMAT,
+            false,
+            ShaderId::Model.descriptor(ShaderId::Model.embedded()),
+        );
+        let transparent_pipeline = create_model_pipeline(
+            &device,
+            &render_pipeline_layout,
+            HDR_FORMAT,
+            true,
+            ShaderId::Model.descriptor(ShaderId::Model.embedded()),
+        );
 
-            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        let point_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Point Pipeline Layout"),
                 bind_group_layouts: &[&camera_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
-            create_render_pipeline(
-                &device,
-                &layout,
-                wgpu::PrimitiveTopology::PointList,
-                config.format,
-                Some(texture::Texture::DEPTH_FORMAT),
-                &[model::PointVertex::desc()],
-                shader,
-            )
-        };
+        let point_render_pipeline = create_point_pipeline(
+            &device,
+            &point_pipeline_layout,
+            HDR_FORMAT,
+            ShaderId::Point.descriptor(ShaderId::Point.embedded()),
+        );
+
+        let shaders = ShaderWatcher::new();
 
         let points = [
             PointVertex {
@@ -412,11 +445,9 @@ impl Renderer {
         ]
         .to_vec();
 
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(points.as_slice()),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let mut point_buffer =
+            DynamicBuffer::with_data(&device, "Point Buffer", wgpu::BufferUsages::VERTEX, points);
+        point_buffer.upload(&device, &queue);
 
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
@@ -426,29 +457,28 @@ impl Renderer {
 
         let num_indices = INDICES.len() as u32;
 
-        let egui_renderer = egui_wgpu::renderer::Renderer::new(&device, config.format, None, 1);
-
-        let obj_model =
-            crate::resources::load_model("cube.obj", &device, &queue, &texture_bind_group_layout)
-                .await
-                .unwrap();
+        let egui_renderer = egui_wgpu::renderer::Renderer::new(&device, output_format, None, 1);
 
-        Self {
+        let mut renderer = Self {
             surface,
             device,
             queue,
             config,
+            output_format,
             size,
-            clear_color,
             adapter,
             instance,
+            render_pipeline_layout,
             render_pipeline,
+            transparent_pipeline,
+            queues: RenderQueues::default(),
+            point_pipeline_layout,
             point_render_pipeline,
+            shaders,
             num_indices,
             index_buffer,
-            diffuse_bind_group,
-            diffuse_texture,
             camera,
+            view_camera: camera,
             camera_uniform,
             camera_buffer,
             camera_bind_group,
@@ -456,14 +486,119 @@ impl Renderer {
             egui_renderer,
             instances,
             instance_buffer,
+            last_pick: None,
+            gizmo,
+            grid,
+            post,
+            lights,
+            shadows,
+            skybox,
+            environment,
+            render_settings,
+            wireframe,
             depth_texture,
-            obj_model,
-            vertex_buffer,
-            points,
+            scene,
+            point_buffer,
             mouse,
+            statuses: std::iter::once(sky_status)
+                .chain(warnings.into_iter().map(AppStatus::Warning))
+                .collect(),
+            profiler,
+        };
+        renderer.add_model("Cubes", cube, None, true);
+        renderer.add_light(
+            Light {
+                color: [1.0, 0.8, 0.6],
+                ..Light::new(LightKind::Point)
+            },
+            Transform {
+                translation: Vector3::new(2.0, 2.0, 2.0),
+                ..Default::default()
+            },
+        );
+        renderer
+    }
+
+    /// Adds `model` to the scene under a new node.
+    pub fn add_model(
+        &mut self,
+        name: &str,
+        model: model::Model,
+        parent: Option<NodeId>,
+        instanced: bool,
+    ) -> NodeId {
+        self.wireframe.add_model(&self.device, &model);
+        self.render_settings
+            .reserve(&self.device, model.meshes.len());
+        let model = self.scene.add_model(model);
+        self.scene
+            .add_node(name, parent, vec![Component::Model { model, instanced }])
+    }
+
+    /// Adds `light` with a node of its own among the roots, unless the light
+    /// list is full.
+    pub fn add_light(&mut self, light: Light, transform: Transform) -> Option<NodeId> {
+        let index = self.lights.add(light)?;
+        let name = format!("{} Light", light.kind.name());
+        let id = self
+            .scene
+            .add_node(name, None, vec![Component::Light(index)]);
+        self.scene.node_mut(id).transform = transform;
+        Some(id)
+    }
+
+    /// Removes the light, along with its node when that holds nothing else.
+    pub fn remove_light(&mut self, index: usize) {
+        let light = Component::Light(index);
+        let node = self.scene.find(light).filter(|&id| {
+            self.scene.children(id).is_empty() && self.scene.node(id).components == [light]
+        });
+        match node {
+            Some(id) => self.remove_node(id),
+            None => self.drop_light(index),
+        }
+    }
+
+    fn drop_light(&mut self, index: usize) {
+        self.lights.remove(index);
+        self.scene.remove_light(index);
+    }
+
+    /// Removes the node and its subtree, along with the lights they held.
+    pub fn remove_node(&mut self, id: NodeId) {
+        let mut lights = self
+            .scene
+            .remove_node(id)
+            .into_iter()
+            .filter_map(|component| match component {
+                Component::Light(index) => Some(index),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // The last first, so the indices left to remove stay valid.
+        lights.sort_unstable_by(|a, b| b.cmp(a));
+        for index in lights {
+            self.drop_light(index);
         }
     }
 
+    pub fn remove_mesh(&mut self, model: usize, mesh: usize) {
+        self.scene.models[model].meshes.remove(mesh);
+        self.wireframe.remove_mesh(model, mesh);
+    }
+
+    pub fn take_statuses(&mut self) -> Vec<AppStatus> {
+        std::mem::take(&mut self.statuses)
+    }
+
+    pub fn points(&self) -> usize {
+        self.point_buffer.len()
+    }
+
+    pub fn clear_points(&mut self) {
+        self.point_buffer.clear();
+    }
+
     pub fn input(&mut self, event: &WindowEvent) -> bool {
         self.camera_controller.process_events(event);
         self.mouse.process_events(event);
@@ -479,6 +614,11 @@ impl Renderer {
 
             self.depth_texture =
                 texture::Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
+            self.post
+                .resize(&self.device, new_size.width, new_size.height);
+
+            self.mouse
+                .resize(new_size.width as f32, new_size.height as f32);
 
             self.surface.configure(&self.device, &self.config);
         }
@@ -488,29 +628,211 @@ impl Renderer {
         self.mouse
     }
 
-    pub fn camera(&self) -> Camera {
-        self.camera
+    pub fn last_pick(&self) -> Option<PickHit> {
+        self.last_pick
+    }
+
+    /// Starts dragging the gizmo handle under the cursor, if any.
+    pub fn begin_gizmo_drag(&mut self) -> bool {
+        self.gizmo
+            .begin_drag(&self.view_camera, &self.mouse, &self.instances)
+    }
+
+    /// Casts a ray through the cursor and selects the instance or light
+    /// under it, whichever is closer.
+    pub fn pick_at_cursor(&mut self) -> Option<PickHit> {
+        let ray = Ray::from_ndc(&self.view_camera, self.mouse.pos_ndc());
+        let light = ray.and_then(|ray| self.lights.pick(&ray, &self.view_camera));
+        self.last_pick = ray.and_then(|ray| {
+            self.scene
+                .instanced_models()
+                .filter_map(|(node, model)| {
+                    let space = self.scene.world(node);
+                    picking::pick_instance(&ray, model, &space, self.instances.instances())
+                })
+                .min_by(|a, b| a.distance.total_cmp(&b.distance))
+        });
+
+        match (light, self.last_pick) {
+            (Some((light, distance)), hit) if hit.is_none_or(|hit| distance < hit.distance) => {
+                self.last_pick = None;
+                self.lights.select(Some(light));
+            }
+            _ => self.lights.select(None),
+        }
+        self.instances
+            .select(self.last_pick.map(|hit| hit.instance));
+
+        self.last_pick
+    }
+
+    /// Rebuilds the pipelines using `shader` from its preprocessed source. The
+    /// current pipeline is kept when the new source fails to validate.
+    pub fn reload_shader(
+        &mut self,
+        shader: ShaderId,
+        preprocessed: &Preprocessed,
+    ) -> Result<(), ShaderError> {
+        shaders::validate(shader, preprocessed)?;
+
+        let descriptor = shader.descriptor(&preprocessed.source);
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let transparent_pipeline = (shader == ShaderId::Model).then(|| {
+            create_model_pipeline(
+                &self.device,
+                &self.render_pipeline_layout,
+                HDR_FORMAT,
+                true,
+                descriptor.clone(),
+            )
+        });
+        let pipeline = match shader {
+            ShaderId::Model => create_model_pipeline(
+                &self.device,
+                &self.render_pipeline_layout,
+                HDR_FORMAT,
+                false,
+                descriptor,
+            ),
+            ShaderId::Point => create_point_pipeline(
+                &self.device,
+                &self.point_pipeline_layout,
+                HDR_FORMAT,
+                descriptor,
+            ),
+            ShaderId::Gizmo => self.gizmo.create_pipeline(&self.device, descriptor),
+            ShaderId::Wireframe => self.wireframe.create_pipeline(&self.device, descriptor),
+            ShaderId::Grid => self.grid.create_pipeline(&self.device, descriptor),
+            ShaderId::Shadow => self.shadows.create_pipeline(&self.device, descriptor),
+            ShaderId::Tonemap | ShaderId::Fxaa | ShaderId::Vignette => {
+                self.post.create_pipeline(&self.device, descriptor)
+            }
+            ShaderId::BloomDownsample | ShaderId::BloomUpsample => {
+                self.post
+                    .bloom
+                    .create_pipeline(&self.device, shader, descriptor)
+            }
+            ShaderId::Skybox | ShaderId::EquirectToCube => {
+                self.skybox
+                    .create_pipeline(&self.device, shader, descriptor)
+            }
+            ShaderId::Irradiance | ShaderId::Prefilter | ShaderId::BrdfLut => self
+                .environment
+                .create_pipeline(&self.device, shader, descriptor),
+        };
+
+        if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+            return Err(ShaderError::new(shader, error.to_string()));
+        }
+
+        match shader {
+            ShaderId::Model => {
+                self.render_pipeline = pipeline;
+                self.transparent_pipeline = transparent_pipeline.unwrap();
+            }
+            ShaderId::Point => self.point_render_pipeline = pipeline,
+            ShaderId::Gizmo => self.gizmo.set_pipeline(pipeline),
+            ShaderId::Wireframe => self.wireframe.set_pipeline(pipeline),
+            ShaderId::Grid => self.grid.set_pipeline(pipeline),
+            ShaderId::Shadow => self.shadows.set_pipeline(pipeline),
+            ShaderId::Tonemap | ShaderId::Fxaa | ShaderId::Vignette => {
+                self.post.set_pipeline(shader, pipeline)
+            }
+            ShaderId::BloomDownsample | ShaderId::BloomUpsample => {
+                self.post.bloom.set_pipeline(shader, pipeline)
+            }
+            ShaderId::Skybox | ShaderId::EquirectToCube => {
+                self.skybox.set_pipeline(shader, pipeline)
+            }
+            ShaderId::Irradiance | ShaderId::Prefilter | ShaderId::BrdfLut => {
+                self.environment.set_pipeline(shader, pipeline);
+                self.environment
+                    .generate(&self.device, &self.queue, self.skybox.cubemap());
+            }
+        }
+
+        Ok(())
     }
 
     pub fn update(&mut self) {
+        let start = Instant::now();
+
+        for (shader, result) in self.shaders.poll() {
+            let result = result.and_then(|preprocessed| self.reload_shader(shader, &preprocessed));
+            let status = self.shaders.report(shader, result);
+            self.statuses.push(status);
+        }
+
         // FIXME !!!
-        if self.mouse.pressed() && !self.mouse.released() {
+        if self.mouse.pressed() && !self.mouse.released() && !self.gizmo.is_dragging() {
             let point = PointVertex {
                 position: [self.mouse.pos_ndc().x, self.mouse.pos_ndc().y, 0.0],
             };
-            self.points.push(point);
-
-            self.vertex_buffer =
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Vertex Buffer"),
-                        contents: bytemuck::cast_slice(self.points.as_slice()),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    });
+            self.point_buffer.push(point);
+        }
+
+        if self.skybox.sync(&self.device, &self.queue) {
+            self.environment
+                .generate(&self.device, &self.queue, self.skybox.cubemap());
         }
 
         self.camera_controller.update_camera(&mut self.camera);
-        self.camera_uniform.update_view_proj(&self.camera);
+
+        self.scene.update();
+        self.update_camera();
+        self.lights.place(self.scene.lights());
+        self.gizmo.set_space(self.scene.instance_space());
+
+        self.gizmo.update(
+            &self.view_camera,
+            &self.mouse,
+            Vector2::new(self.size.width as f32, self.size.height as f32),
+            &mut self.instances,
+        );
+        self.gizmo
+            .extend(self.lights.gizmo_lines(&self.view_camera));
+
+        self.instances.refresh();
+        // Culled and re-sorted every frame since the camera moves.
+        self.queues.update(
+            &self.view_camera,
+            &self.shadows.settings.light_view_proj(),
+            &self.scene,
+            &self.instances,
+        );
+        self.instance_buffer
+            .replace(self.queues.instances().iter().copied());
+        for model in &mut self.scene.models {
+            for material in &mut model.materials {
+                material.prepare(&self.queue);
+            }
+        }
+
+        self.point_buffer.upload(&self.device, &self.queue);
+        self.instance_buffer.upload(&self.device, &self.queue);
+        self.gizmo.prepare(&self.device, &self.queue);
+        self.grid.prepare(&self.queue);
+        self.lights.prepare(&self.queue);
+        self.shadows
+            .prepare(&self.device, &self.queue, &self.lights);
+        self.post.prepare(&self.queue);
+        self.environment.prepare(&self.queue);
+
+        self.profiler.record("Update", start);
+    }
+
+    /// Places the camera at its node and uploads it. Called again after the
+    /// UI so camera edits show in the same frame.
+    pub fn update_camera(&mut self) {
+        let placement = self
+            .scene
+            .find(Component::Camera)
+            .map_or(Matrix4::identity(), |node| self.scene.world(node));
+        self.view_camera = self.camera.transformed(&placement);
+
+        self.render_settings.update(&self.queue, &self.view_camera);
+
+        self.camera_uniform.update_view_proj(&self.view_camera);
         self.queue.write_buffer(
             &self.camera_buffer,
             0,
@@ -518,32 +840,61 @@ impl Renderer {
         );
     }
 
+    fn draw_queue<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, draws: &[Draw]) {
+        for draw in draws {
+            let model = &self.scene.models[draw.model];
+            let mesh = &model.meshes[draw.mesh];
+            self.render_settings.bind(render_pass, 2, draw.mesh);
+            render_pass.draw_mesh_instanced(
+                mesh,
+                &model.materials[mesh.material],
+                draw.instances.clone(),
+                &self.camera_bind_group,
+            );
+        }
+    }
+
     pub fn render(
         &mut self,
         egui_textures_delta: &TexturesDelta,
         egui_clipped_primitves: &[ClippedPrimitive],
         egui_screen_descriptor: &ScreenDescriptor,
     ) -> Result<(), wgpu::SurfaceError> {
+        let start = Instant::now();
         let output = self.surface.get_current_texture()?;
+        self.profiler.record("Acquire", start);
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(self.output_format),
+            ..Default::default()
+        });
 
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+        self.profiler.begin_gpu(&mut encoder);
+
+        let start = Instant::now();
+        self.shadows.render(
+            &mut encoder,
+            &self.scene.models,
+            (!self.instance_buffer.is_empty()).then(|| self.instance_buffer.slice()),
+            &self.queues.shadow_casters,
+        );
+        self.profiler.end_gpu_pass(&mut encoder, "Shadows");
+        self.profiler.record("Shadows", start);
 
+        let start = Instant::now();
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: self.post.hdr_view(),
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        load: wgpu::LoadOp::Clear(self.skybox.clear_color()),
                         store: true,
                     },
                 })],
@@ -557,21 +908,69 @@ impl Renderer {
                 }),
             });
 
-            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.draw_model_instanced(
-                &self.obj_model,
-                0..self.instances.len() as u32,
-                &self.camera_bind_group,
-            );
-
-            render_pass.set_pipeline(&self.point_render_pipeline);
-            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            if !self.instance_buffer.is_empty() {
+                render_pass.set_vertex_buffer(1, self.instance_buffer.slice());
+                render_pass.set_pipeline(&self.render_pipeline);
+                render_pass.set_bind_group(3, self.shadows.lighting_bind_group(), &[]);
+                self.draw_queue(&mut render_pass, &self.queues.opaque);
+
+                if self.render_settings.mode == RenderMode::Wireframe {
+                    self.wireframe.draw(
+                        &mut render_pass,
+                        self.queues.opaque.iter().chain(&self.queues.transparent),
+                        &self.camera_bind_group,
+                    );
+                }
+            }
+
+            self.skybox.draw(&mut render_pass, &self.camera_bind_group);
+            self.grid.draw(&mut render_pass, &self.camera_bind_group);
+
+            if !self.point_buffer.is_empty() && self.scene.find(Component::Points).is_some() {
+                render_pass.set_pipeline(&self.point_render_pipeline);
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.point_buffer.slice());
+
+                render_pass.draw(0..self.point_buffer.len() as u32, 0..1);
+            }
+
+            // Last, so they blend over everything else.
+            if !self.queues.transparent.is_empty() {
+                render_pass.set_vertex_buffer(1, self.instance_buffer.slice());
+                render_pass.set_pipeline(&self.transparent_pipeline);
+                render_pass.set_bind_group(3, self.shadows.lighting_bind_group(), &[]);
+                self.draw_queue(&mut render_pass, &self.queues.transparent);
+            }
+        }
+        self.profiler.end_gpu_pass(&mut encoder, "Scene");
+        self.profiler.record("Scene", start);
+
+        let start = Instant::now();
+        self.post.render(&mut encoder, &view);
+        self.profiler.end_gpu_pass(&mut encoder, "Post");
+        self.profiler.record("Post", start);
+
+        let start = Instant::now();
+        if self.gizmo.has_geometry() {
+            let mut gizmo_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Gizmo Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
 
-            render_pass.draw(0..self.points.len() as u32, 0..1);
+            self.gizmo.draw(&mut gizmo_pass, &self.camera_bind_group);
         }
+        self.profiler.end_gpu_pass(&mut encoder, "Gizmo");
+        self.profiler.record("Gizmo", start);
 
+        let start = Instant::now();
         for (id, delta) in &egui_textures_delta.set {
             self.egui_renderer
                 .update_texture(&self.device, &self.queue, *id, delta);
@@ -605,7 +1004,12 @@ impl Renderer {
                 egui_screen_descriptor,
             );
         }
+        self.profiler.end_gpu_pass(&mut encoder, "Egui");
+        self.profiler.record("Egui", start);
 
+        self.profiler.resolve_gpu(&mut encoder);
+
+        let start = Instant::now();
         self.queue.submit(std::iter::once(encoder.finish()));
 
         for delta in &egui_textures_delta.free {
@@ -613,6 +1017,10 @@ impl Renderer {
         }
 
         output.present();
+        self.profiler.record("Submit", start);
+
+        self.profiler.end_frame(&self.device);
+        self.mouse.end_frame();
 
         Ok(())
     }
@@ -3,7 +3,11 @@ use std::io::{BufReader, Cursor};
 use cfg_if::cfg_if;
 use wgpu::util::DeviceExt;
 
-use crate::{model, texture};
+use crate::{
+    bounds::{Aabb, Sphere},
+    environment::Environment,
+    model, texture,
+};
 
 pub async fn load_string(file_name: &str) -> anyhow::Result<String> {
     cfg_if! {
@@ -53,12 +57,50 @@ pub async fn load_texture(
     texture::Texture::from_bytes(device, queue, &data, file_name)
 }
 
+pub async fn load_linear_texture(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<texture::Texture> {
+    let data = load_binary(file_name).await?;
+    texture::Texture::from_bytes_linear(device, queue, &data, file_name)
+}
+
+/// Loads six images, in the +X, -X, +Y, -Y, +Z, -Z order, into a cubemap.
+pub async fn load_cubemap(
+    file_names: [&str; 6],
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<texture::Texture> {
+    let mut faces = Vec::with_capacity(6);
+    for file_name in file_names {
+        let data = load_binary(file_name).await?;
+        faces.push(image::load_from_memory(&data)?);
+    }
+    let faces: [image::DynamicImage; 6] = faces.try_into().unwrap();
+    texture::Texture::cubemap_from_images(device, queue, &faces, file_names[0])
+}
+
+pub async fn load_hdr_texture(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<texture::Texture> {
+    let data = load_binary(file_name).await?;
+    texture::Texture::from_hdr_bytes(device, queue, &data, file_name)
+}
+
+/// Loads an OBJ model along with warnings about the parts that were missing
+/// or unreadable and were replaced by defaults.
 pub async fn load_model(
     file_name: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
     layout: &wgpu::BindGroupLayout,
-) -> anyhow::Result<model::Model> {
+    environment: &Environment,
+    placeholder: &texture::Texture,
+) -> anyhow::Result<(model::Model, Vec<String>)> {
+    let mut warnings = Vec::new();
     let obj_text = load_string(file_name).await?;
     let obj_cursor = Cursor::new(obj_text);
     let mut obj_reader = BufReader::new(obj_cursor);
@@ -71,41 +113,92 @@ pub async fn load_model(
             ..Default::default()
         },
         |p| async move {
-            let mat_text = load_string(&p).await.unwrap();
-            tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
+            match load_string(&p).await {
+                Ok(mat_text) => tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text))),
+                Err(_) => Err(tobj::LoadError::OpenFileFailed),
+            }
         },
     )
     .await?;
 
+    let obj_materials = obj_materials.unwrap_or_else(|e| {
+        warnings.push(format!("{file_name}: materials not loaded: {e}"));
+        Vec::new()
+    });
+
     let mut materials = Vec::new();
-    for m in obj_materials? {
-        let diffuse_texture = load_texture(&m.diffuse_texture.unwrap(), device, queue).await?;
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+    for m in obj_materials {
+        let (base_color, texture_alpha) =
+            load_base_color(m.diffuse_texture.as_deref(), device, queue, &mut warnings).await;
+        let factors = material_factors(&m, texture_alpha);
+        let textures = model::MaterialTextures {
+            base_color,
+            metallic_roughness: load_optional(
+                m.unknown_param.get("map_Pr").map(String::as_str),
+                device,
+                queue,
+                false,
+                &mut warnings,
+            )
+            .await,
+            emissive: load_optional(
+                m.unknown_param.get("map_Ke").map(String::as_str),
+                device,
+                queue,
+                true,
+                &mut warnings,
+            )
+            .await,
+            occlusion: load_optional(
+                m.ambient_texture.as_deref(),
+                device,
+                queue,
+                false,
+                &mut warnings,
+            )
+            .await,
+        };
+
+        materials.push(model::Material::new(
+            device,
+            placeholder,
+            m.name,
+            factors,
+            textures,
             layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: None,
-        });
-
-        materials.push(model::Material {
-            name: m.name,
-            diffuse_texture,
-            bind_group,
-        })
+            environment,
+        ));
+    }
+
+    if materials.is_empty() {
+        materials.push(model::Material::new(
+            device,
+            placeholder,
+            "default".to_owned(),
+            model::MaterialFactors::default(),
+            model::MaterialTextures::default(),
+            layout,
+            environment,
+        ));
     }
 
     let meshes = models
         .into_iter()
-        .map(|m| {
-            let vertices = (0..m.mesh.positions.len() / 3)
+        .map(|mut m| {
+            let vertex_count = m.mesh.positions.len() / 3;
+            if m.mesh.texcoords.len() < vertex_count * 2 {
+                warnings.push(format!(
+                    "{file_name}: {} has no texture coordinates",
+                    m.name
+                ));
+                m.mesh.texcoords = vec![0.0; vertex_count * 2];
+            }
+            if m.mesh.normals.len() < vertex_count * 3 {
+                warnings.push(format!("{file_name}: {} has no normals", m.name));
+                m.mesh.normals = [0.0, 1.0, 0.0].repeat(vertex_count);
+            }
+
+            let vertices = (0..vertex_count)
                 .map(|i| model::ModelVertex {
                     position: [
                         m.mesh.positions[i * 3],
@@ -132,15 +225,168 @@ pub async fn load_model(
                 usage: wgpu::BufferUsages::INDEX,
             });
 
+            let positions = vertices.iter().map(|v| v.position).collect::<Vec<_>>();
+            let bounds = Aabb::from_points(positions.iter().copied());
+            let sphere = Sphere::from_points(positions.iter().copied());
+
             model::Mesh {
                 name: file_name.to_string(),
                 vertex_buffer,
                 index_buffer,
                 num_elements: m.mesh.indices.len() as u32,
-                material: m.mesh.material_id.unwrap_or(0),
+                material: m
+                    .mesh
+                    .material_id
+                    .filter(|&id| id < materials.len())
+                    .unwrap_or(0),
+                positions,
+                indices: m.mesh.indices,
+                bounds,
+                sphere,
+                visible: true,
             }
         })
         .collect::<Vec<_>>();
 
-    Ok(model::Model { meshes, materials })
+    Ok((model::Model { meshes, materials }, warnings))
+}
+
+/// A texture that fails to load is left out, falling back to the factors.
+async fn load_optional(
+    file_name: Option<&str>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    srgb: bool,
+    warnings: &mut Vec<String>,
+) -> Option<texture::Texture> {
+    let file_name = file_name?;
+    let texture = if srgb {
+        load_texture(file_name, device, queue).await
+    } else {
+        load_linear_texture(file_name, device, queue).await
+    };
+    texture
+        .map_err(|e| warnings.push(format!("{file_name}: {e}")))
+        .ok()
+}
+
+/// Loads the base color texture along with the alpha mode its alpha channel
+/// calls for, see `image_alpha_mode`.
+async fn load_base_color(
+    file_name: Option<&str>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    warnings: &mut Vec<String>,
+) -> (Option<texture::Texture>, Option<model::AlphaMode>) {
+    let Some(file_name) = file_name else {
+        return (None, None);
+    };
+    let loaded = async {
+        let data = load_binary(file_name).await?;
+        let image = image::load_from_memory(&data)?;
+        let texture = texture::Texture::from_image(device, queue, &image, Some(file_name))?;
+        anyhow::Ok((texture, image_alpha_mode(&image)))
+    }
+    .await;
+
+    match loaded {
+        Ok((texture, alpha_mode)) => (Some(texture), alpha_mode),
+        Err(e) => {
+            warnings.push(format!("{file_name}: {e}"));
+            (None, None)
+        }
+    }
+}
+
+/// `Mask` when every texel is either fully transparent or opaque, `Blend`
+/// when some are in between, and `None` when all are opaque.
+fn image_alpha_mode(image: &image::DynamicImage) -> Option<model::AlphaMode> {
+    if !image.color().has_alpha() {
+        return None;
+    }
+    let mut mode = None;
+    for pixel in image.to_rgba8().pixels() {
+        match pixel[3] {
+            255 => {}
+            0 => mode = Some(model::AlphaMode::Mask(0.5)),
+            _ => return Some(model::AlphaMode::Blend),
+        }
+    }
+    mode
+}
+
+/// Reads the PBR extension of MTL (`Pr`, `Pm`, `Ke`) and falls back to the
+/// Blender export convention for the roughness, `Ns = (1 - roughness)^2 * 1000`.
+/// The packed metallic-roughness texture is taken from `map_Pr` and the
+/// occlusion texture from `map_Ka`. A dissolve below one makes the material
+/// blend, while a `map_d` cuts it out at the base color's alpha. Without
+/// either, the alpha mode follows `texture_alpha`, detected in the base color
+/// texture. The material inspector can override it.
+fn material_factors(
+    m: &tobj::Material,
+    texture_alpha: Option<model::AlphaMode>,
+) -> model::MaterialFactors {
+    let defaults = model::MaterialFactors::default();
+    let param = |key: &str| m.unknown_param.get(key);
+    let scalar = |key: &str| param(key).and_then(|value| value.trim().parse::<f32>().ok());
+
+    let [r, g, b] = m.diffuse.unwrap_or([1.0; 3]);
+    let roughness = scalar("Pr").or_else(|| {
+        m.shininess
+            .map(|ns| 1.0 - (ns / 1000.0).clamp(0.0, 1.0).sqrt())
+    });
+    let emissive = param("Ke").and_then(|value| {
+        let values = value
+            .split_whitespace()
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        values.try_into().ok()
+    });
+    let dissolve = m.dissolve.unwrap_or(1.0);
+    let alpha_mode = if dissolve < 1.0 {
+        model::AlphaMode::Blend
+    } else if m.dissolve_texture.is_some() {
+        model::AlphaMode::Mask(0.5)
+    } else {
+        texture_alpha.unwrap_or(defaults.alpha_mode)
+    };
+
+    model::MaterialFactors {
+        base_color: [r, g, b, dissolve],
+        metallic: scalar("Pm").unwrap_or(defaults.metallic),
+        roughness: roughness.unwrap_or(defaults.roughness),
+        emissive: emissive.unwrap_or(defaults.emissive),
+        occlusion_strength: defaults.occlusion_strength,
+        alpha_mode,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::image_alpha_mode;
+    use crate::model::AlphaMode;
+
+    fn image_with_alphas(alphas: &[u8]) -> image::DynamicImage {
+        let mut image = image::RgbaImage::new(alphas.len() as u32, 1);
+        for (pixel, &alpha) in image.pixels_mut().zip(alphas) {
+            *pixel = image::Rgba([255, 255, 255, alpha]);
+        }
+        image::DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn alpha_mode_follows_the_texture_alpha() {
+        let rgb = image::DynamicImage::ImageRgb8(image::RgbImage::new(2, 2));
+        assert_eq!(image_alpha_mode(&rgb), None);
+        assert_eq!(image_alpha_mode(&image_with_alphas(&[255, 255])), None);
+        assert_eq!(
+            image_alpha_mode(&image_with_alphas(&[255, 0, 255])),
+            Some(AlphaMode::Mask(0.5))
+        );
+        assert_eq!(
+            image_alpha_mode(&image_with_alphas(&[0, 128, 255])),
+            Some(AlphaMode::Blend)
+        );
+    }
 }
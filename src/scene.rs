@@ -0,0 +1,357 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Quaternion, Vector3};
+
+use crate::model::Model;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::zero(),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+/// What a node brings to the scene. The node's world matrix places it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component {
+    /// `Scene::models[model]`, drawn once at the node, or at every instance
+    /// of the instance set when `instanced`.
+    Model { model: usize, instanced: bool },
+    /// `Lights::lights[index]`, shining from the node's origin along its -Z
+    /// axis.
+    Light(usize),
+    /// The clicked points. They are already in clip space, so only the node's
+    /// visibility applies.
+    Points,
+    /// The view camera, whose eye and target are relative to the node.
+    Camera,
+}
+
+impl Component {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Model { .. } => "Model",
+            Self::Light(_) => "Light",
+            Self::Points => "Points",
+            Self::Camera => "Camera",
+        }
+    }
+}
+
+pub struct Node {
+    pub name: String,
+    pub transform: Transform,
+    /// Hides the node and its whole subtree.
+    pub visible: bool,
+    pub components: Vec<Component>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Matrix4<f32>,
+    world_visible: bool,
+}
+
+/// A tree of nodes with local transforms, and the models they draw. World
+/// matrices are only valid after `update`. Removed nodes leave a hole so the
+/// ids of the others stay valid.
+pub struct Scene {
+    nodes: Vec<Option<Node>>,
+    roots: Vec<NodeId>,
+    pub models: Vec<Model>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            models: Vec::new(),
+        }
+    }
+
+    pub fn add_model(&mut self, model: Model) -> usize {
+        self.models.push(model);
+        self.models.len() - 1
+    }
+
+    pub fn add_node(
+        &mut self,
+        name: impl Into<String>,
+        parent: Option<NodeId>,
+        components: Vec<Component>,
+    ) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Some(Node {
+            name: name.into(),
+            transform: Transform::default(),
+            visible: true,
+            components,
+            parent,
+            children: Vec::new(),
+            world: Matrix4::identity(),
+            world_visible: true,
+        }));
+
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+
+        id
+    }
+
+    /// Removes `id` and its whole subtree, returning the components they
+    /// held.
+    pub fn remove_node(&mut self, id: NodeId) -> Vec<Component> {
+        self.detach(id);
+        let mut removed = Vec::new();
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes[id.0].take() {
+                stack.extend(node.children);
+                removed.extend(node.components);
+            }
+        }
+        removed
+    }
+
+    /// Drops the `Light(index)` components and moves the later lights down
+    /// an index, to follow the light list.
+    pub fn remove_light(&mut self, index: usize) {
+        for node in self.nodes.iter_mut().flatten() {
+            node.components
+                .retain(|&component| component != Component::Light(index));
+            for component in &mut node.components {
+                if let Component::Light(i) = component {
+                    if *i > index {
+                        *i -= 1;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        self.nodes[id.0].as_ref().expect("node was removed")
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.nodes[id.0].as_mut().expect("node was removed")
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    /// Whether `ancestor` is `id` or one of its parents.
+    pub fn is_ancestor(&self, ancestor: NodeId, id: NodeId) -> bool {
+        let mut current = Some(id);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.parent(node);
+        }
+        false
+    }
+
+    /// Moves `id` under `parent`, or to the roots. Refused when it would
+    /// make a cycle.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if parent.is_some_and(|parent| self.is_ancestor(id, parent)) {
+            return false;
+        }
+
+        self.detach(id);
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        self.node_mut(id).parent = parent;
+
+        true
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        match self.parent(id) {
+            Some(parent) => self.node_mut(parent).children.retain(|&child| child != id),
+            None => self.roots.retain(|&root| root != id),
+        }
+    }
+
+    /// Every node, parents before their children.
+    pub fn traverse(&self) -> Vec<NodeId> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = self.roots.iter().rev().copied().collect::<Vec<_>>();
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self.children(id).iter().rev());
+        }
+        order
+    }
+
+    /// Propagates the local transforms and visibility down the tree.
+    pub fn update(&mut self) {
+        for id in self.traverse() {
+            let (parent_world, parent_visible) = match self.parent(id) {
+                Some(parent) => {
+                    let parent = self.node(parent);
+                    (parent.world, parent.world_visible)
+                }
+                None => (Matrix4::identity(), true),
+            };
+
+            let node = self.node_mut(id);
+            node.world = parent_world * node.transform.matrix();
+            node.world_visible = parent_visible && node.visible;
+        }
+    }
+
+    pub fn world(&self, id: NodeId) -> Matrix4<f32> {
+        self.node(id).world
+    }
+
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        self.node(id).world_visible
+    }
+
+    /// The components of the visible nodes, in traversal order.
+    pub fn components(&self) -> impl Iterator<Item = (NodeId, Component)> + '_ {
+        self.traverse()
+            .into_iter()
+            .filter(|&id| self.is_visible(id))
+            .flat_map(|id| {
+                self.node(id)
+                    .components
+                    .iter()
+                    .map(move |&component| (id, component))
+            })
+    }
+
+    /// The first visible node holding `component`.
+    pub fn find(&self, component: Component) -> Option<NodeId> {
+        self.components()
+            .find(|&(_, c)| c == component)
+            .map(|(id, _)| id)
+    }
+
+    /// Where the instance set is placed: the world matrix of the first
+    /// instanced model.
+    pub fn instance_space(&self) -> Matrix4<f32> {
+        self.instanced_models()
+            .next()
+            .map_or(Matrix4::identity(), |(id, _)| self.world(id))
+    }
+
+    /// The lights of the visible nodes, by index, with the nodes' world
+    /// matrices.
+    pub fn lights(&self) -> impl Iterator<Item = (usize, Matrix4<f32>)> + '_ {
+        self.components()
+            .filter_map(|(id, component)| match component {
+                Component::Light(index) => Some((index, self.world(id))),
+                _ => None,
+            })
+    }
+
+    /// The visible nodes drawing a model at every instance.
+    pub fn instanced_models(&self) -> impl Iterator<Item = (NodeId, &Model)> + '_ {
+        self.components()
+            .filter_map(|(id, component)| match component {
+                Component::Model {
+                    model,
+                    instanced: true,
+                } => Some((id, &self.models[model])),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::prelude::*;
+    use cgmath::{Deg, Point3, Quaternion, Vector3};
+
+    use super::{Component, Scene};
+
+    #[test]
+    fn set_parent_refuses_cycles() {
+        let mut scene = Scene::new();
+        let root = scene.add_node("root", None, Vec::new());
+        let child = scene.add_node("child", Some(root), Vec::new());
+        let grandchild = scene.add_node("grandchild", Some(child), Vec::new());
+
+        assert!(!scene.set_parent(root, Some(grandchild)));
+        assert!(!scene.set_parent(child, Some(child)));
+        assert_eq!(scene.parent(root), None);
+        assert_eq!(scene.roots(), &[root]);
+
+        assert!(scene.set_parent(grandchild, None));
+        assert!(scene.set_parent(root, Some(grandchild)));
+        assert_eq!(scene.parent(root), Some(grandchild));
+        assert_eq!(scene.roots(), &[grandchild]);
+        assert_eq!(scene.traverse(), vec![grandchild, root, child]);
+    }
+
+    #[test]
+    fn update_propagates_transforms_and_visibility() {
+        let mut scene = Scene::new();
+        let parent = scene.add_node("parent", None, Vec::new());
+        let child = scene.add_node("child", Some(parent), Vec::new());
+        scene.node_mut(parent).transform = super::Transform {
+            translation: Vector3::new(0.0, 1.0, 0.0),
+            rotation: Quaternion::from_angle_y(Deg(90.0)),
+            scale: Vector3::new(2.0, 2.0, 2.0),
+        };
+        scene.node_mut(child).transform.translation = Vector3::new(1.0, 0.0, 0.0);
+
+        scene.update();
+        let origin = scene.world(child).transform_point(Point3::origin());
+        assert!((origin - Point3::new(0.0, 1.0, -2.0)).magnitude() < 1e-5);
+
+        scene.node_mut(parent).visible = false;
+        scene.update();
+        assert!(!scene.is_visible(child));
+        assert_eq!(scene.components().count(), 0);
+    }
+
+    #[test]
+    fn removing_a_light_shifts_the_later_ones() {
+        let mut scene = Scene::new();
+        let first = scene.add_node("first", None, vec![Component::Light(0)]);
+        let second = scene.add_node("second", None, vec![Component::Light(1)]);
+        let third = scene.add_node("third", None, vec![Component::Light(2)]);
+
+        scene.remove_light(1);
+        assert_eq!(scene.node(first).components, vec![Component::Light(0)]);
+        assert!(scene.node(second).components.is_empty());
+        assert_eq!(scene.node(third).components, vec![Component::Light(1)]);
+        assert_eq!(scene.remove_node(third), vec![Component::Light(1)]);
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{
+    event::AppStatus,
+    preprocessor::{self, Preprocessed},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderId {
+    Model,
+    Point,
+    Gizmo,
+    Wireframe,
+    Grid,
+    Shadow,
+    Tonemap,
+    Fxaa,
+    Vignette,
+    BloomDownsample,
+    BloomUpsample,
+    Skybox,
+    EquirectToCube,
+    Irradiance,
+    Prefilter,
+    BrdfLut,
+}
+
+impl ShaderId {
+    pub const ALL: [ShaderId; 16] = [
+        Self::Model,
+        Self::Point,
+        Self::Gizmo,
+        Self::Wireframe,
+        Self::Grid,
+        Self::Shadow,
+        Self::Tonemap,
+        Self::Fxaa,
+        Self::Vignette,
+        Self::BloomDownsample,
+        Self::BloomUpsample,
+        Self::Skybox,
+        Self::EquirectToCube,
+        Self::Irradiance,
+        Self::Prefilter,
+        Self::BrdfLut,
+    ];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Model => "shader.wgsl",
+            Self::Point => "point.wgsl",
+            Self::Gizmo => "gizmo.wgsl",
+            Self::Wireframe => "wireframe.wgsl",
+            Self::Grid => "grid.wgsl",
+            Self::Shadow => "shadow.wgsl",
+            Self::Tonemap => "tonemap.wgsl",
+            Self::Fxaa => "fxaa.wgsl",
+            Self::Vignette => "vignette.wgsl",
+            Self::BloomDownsample => "bloom_downsample.wgsl",
+            Self::BloomUpsample => "bloom_upsample.wgsl",
+            Self::Skybox => "skybox.wgsl",
+            Self::EquirectToCube => "equirect_to_cube.wgsl",
+            Self::Irradiance => "irradiance.wgsl",
+            Self::Prefilter => "prefilter.wgsl",
+            Self::BrdfLut => "brdf_lut.wgsl",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Model => "Normal Shader",
+            Self::Point => "Point Shader",
+            Self::Gizmo => "Gizmo Shader",
+            Self::Wireframe => "Wireframe Shader",
+            Self::Grid => "Grid Shader",
+            Self::Shadow => "Shadow Shader",
+            Self::Tonemap => "Tone Mapping Shader",
+            Self::Fxaa => "FXAA Shader",
+            Self::Vignette => "Vignette Shader",
+            Self::BloomDownsample => "Bloom Downsample Shader",
+            Self::BloomUpsample => "Bloom Upsample Shader",
+            Self::Skybox => "Skybox Shader",
+            Self::EquirectToCube => "Equirect To Cube Shader",
+            Self::Irradiance => "Irradiance Shader",
+            Self::Prefilter => "Prefilter Shader",
+            Self::BrdfLut => "BRDF LUT Shader",
+        }
+    }
+
+    /// The source compiled into the binary, preprocessed by `build.rs`.
+    pub fn embedded(&self) -> &'static str {
+        macro_rules! shader {
+            ($name:literal) => {
+                include_str!(concat!(env!("OUT_DIR"), "/shaders/", $name))
+            };
+        }
+
+        match self {
+            Self::Model => shader!("shader.wgsl"),
+            Self::Point => shader!("point.wgsl"),
+            Self::Gizmo => shader!("gizmo.wgsl"),
+            Self::Wireframe => shader!("wireframe.wgsl"),
+            Self::Grid => shader!("grid.wgsl"),
+            Self::Shadow => shader!("shadow.wgsl"),
+            Self::Tonemap => shader!("tonemap.wgsl"),
+            Self::Fxaa => shader!("fxaa.wgsl"),
+            Self::Vignette => shader!("vignette.wgsl"),
+            Self::BloomDownsample => shader!("bloom_downsample.wgsl"),
+            Self::BloomUpsample => shader!("bloom_upsample.wgsl"),
+            Self::Skybox => shader!("skybox.wgsl"),
+            Self::EquirectToCube => shader!("equirect_to_cube.wgsl"),
+            Self::Irradiance => shader!("irradiance.wgsl"),
+            Self::Prefilter => shader!("prefilter.wgsl"),
+            Self::BrdfLut => shader!("brdf_lut.wgsl"),
+        }
+    }
+
+    pub fn descriptor<'a>(&self, source: &'a str) -> wgpu::ShaderModuleDescriptor<'a> {
+        wgpu::ShaderModuleDescriptor {
+            label: Some(self.label()),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShaderError {
+    pub shader: ShaderId,
+    /// The file the error points into, which may be an included one.
+    pub file: String,
+    pub message: String,
+    /// 1-based line and column, when the error points into the source.
+    pub location: Option<(u32, u32)>,
+}
+
+impl ShaderError {
+    pub fn new(shader: ShaderId, message: String) -> Self {
+        Self {
+            shader,
+            file: shader.file_name().to_owned(),
+            message,
+            location: None,
+        }
+    }
+}
+
+/// Parses and validates `shader` with naga so errors can be reported with a
+/// location in the original, unpreprocessed files before wgpu sees the shader.
+pub fn validate(shader: ShaderId, preprocessed: &Preprocessed) -> Result<(), ShaderError> {
+    let source = preprocessed.source.as_str();
+    let error = |message: String, location: Option<naga::SourceLocation>| {
+        let mut error = ShaderError::new(shader, message);
+        if let Some(location) = location {
+            if let Some((file, line)) = preprocessed.origin(location.line_number) {
+                error.file = file.to_owned();
+                error.location = Some((line, location.line_position));
+            }
+        }
+        error
+    };
+
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|e| error(e.message().to_owned(), e.location(source)))?;
+
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|e| error(e.emit_to_string(source), e.location(source)))?;
+
+    Ok(())
+}
+
+/// Polls the shader sources on disk and hands back the preprocessed entry
+/// shaders affected by a change, including changes to included files.
+pub struct ShaderWatcher {
+    pub enabled: bool,
+    dir: PathBuf,
+    last_poll: Instant,
+    modified: HashMap<String, SystemTime>,
+    dependencies: HashMap<ShaderId, Vec<String>>,
+    errors: HashMap<ShaderId, ShaderError>,
+}
+
+impl ShaderWatcher {
+    pub fn new() -> Self {
+        let mut watcher = Self {
+            enabled: cfg!(debug_assertions),
+            dir: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/shaders"),
+            last_poll: Instant::now(),
+            modified: HashMap::new(),
+            dependencies: HashMap::new(),
+            errors: HashMap::new(),
+        };
+
+        // The embedded sources match what is on disk at startup.
+        watcher.modified = watcher.scan();
+        for shader in ShaderId::ALL {
+            if let Ok(preprocessed) = preprocessor::preprocess(&watcher.dir, shader.file_name()) {
+                watcher.dependencies.insert(shader, preprocessed.files);
+            }
+        }
+
+        watcher
+    }
+
+    fn scan(&self) -> HashMap<String, SystemTime> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return HashMap::new();
+        };
+
+        entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let name = entry.file_name().into_string().ok()?;
+                let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+                name.ends_with(".wgsl").then_some((name, modified))
+            })
+            .collect()
+    }
+
+    /// Forces every shader to be reloaded on the next poll.
+    pub fn reload_all(&mut self) {
+        self.modified.clear();
+        if let Some(last_poll) = Instant::now().checked_sub(POLL_INTERVAL) {
+            self.last_poll = last_poll;
+        }
+    }
+
+    pub fn poll(&mut self) -> Vec<(ShaderId, Result<Preprocessed, ShaderError>)> {
+        if !self.enabled || self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let current = self.scan();
+        let changed = current
+            .iter()
+            .filter(|(name, modified)| self.modified.get(*name) != Some(modified))
+            .map(|(name, _)| name.clone())
+            .collect::<HashSet<_>>();
+        self.modified = current;
+
+        if changed.is_empty() {
+            return Vec::new();
+        }
+
+        let mut reloaded = Vec::new();
+        for shader in ShaderId::ALL {
+            let affected = self
+                .dependencies
+                .get(&shader)
+                .is_none_or(|files| files.iter().any(|file| changed.contains(file)));
+            if !affected {
+                continue;
+            }
+
+            let result = match preprocessor::preprocess(&self.dir, shader.file_name()) {
+                Ok(preprocessed) => {
+                    self.dependencies.insert(shader, preprocessed.files.clone());
+                    Ok(preprocessed)
+                }
+                Err(e) => Err(ShaderError {
+                    shader,
+                    file: e.file,
+                    message: e.message,
+                    location: Some((e.line, 1)),
+                }),
+            };
+            reloaded.push((shader, result));
+        }
+
+        reloaded
+    }
+
+    /// Records the outcome of a reload, returning the status to show for it.
+    pub fn report(&mut self, shader: ShaderId, result: Result<(), ShaderError>) -> AppStatus {
+        match result {
+            Ok(()) => {
+                log::info!("{} reloaded", shader.file_name());
+                self.errors.remove(&shader);
+                AppStatus::Info(format!("{} reloaded", shader.file_name()))
+            }
+            Err(error) => {
+                log::warn!("{} failed to reload: {}", shader.file_name(), error.message);
+                // The full message stays in the shader error window.
+                let status = AppStatus::Error(format!(
+                    "{} failed to reload: {}",
+                    shader.file_name(),
+                    error.message.lines().next().unwrap_or_default()
+                ));
+                self.errors.insert(shader, error);
+                status
+            }
+        }
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ShaderError> {
+        ShaderId::ALL
+            .iter()
+            .filter_map(|shader| self.errors.get(shader))
+    }
+}
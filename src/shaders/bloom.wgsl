@@ -0,0 +1,23 @@
+#include "fullscreen.wgsl"
+
+// Shared by the bloom passes. Must match `bloom::BloomUniform`.
+struct BloomSettings {
+  // Of the source texture.
+  texel_size: vec2<f32>,
+  threshold: f32,
+  radius: f32,
+  // Whether this pass reads the HDR scene and applies the threshold.
+  prefilter: u32,
+  _padding: u32,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> bloom: BloomSettings;
+
+fn sample_offset(uv: vec2<f32>, x: f32, y: f32) -> vec3<f32> {
+  return textureSample(source, source_sampler, uv + vec2<f32>(x, y) * bloom.texel_size).rgb;
+}
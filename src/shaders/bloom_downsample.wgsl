@@ -0,0 +1,43 @@
+#include "bloom.wgsl"
+
+// Keeps what is brighter than the threshold, with a soft knee so the cutoff
+// doesn't show.
+fn prefilter(color: vec3<f32>) -> vec3<f32> {
+  let knee = bloom.threshold * 0.5;
+  let brightness = max(color.r, max(color.g, color.b));
+  var soft = clamp(brightness - bloom.threshold + knee, 0.0, 2.0 * knee);
+  soft = soft * soft / (4.0 * knee + 0.00001);
+  let contribution = max(soft, brightness - bloom.threshold) / max(brightness, 0.00001);
+  return color * contribution;
+}
+
+// The 13-tap filter from Jimenez's "Next Generation Post Processing in Call of
+// Duty: Advanced Warfare": five overlapping 4-tap boxes, weighted to the
+// center. Each tap is bilinear, so this covers a 6x6 texel footprint.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let a = sample_offset(in.uv, -2.0, -2.0);
+  let b = sample_offset(in.uv, 0.0, -2.0);
+  let c = sample_offset(in.uv, 2.0, -2.0);
+  let d = sample_offset(in.uv, -2.0, 0.0);
+  let e = sample_offset(in.uv, 0.0, 0.0);
+  let f = sample_offset(in.uv, 2.0, 0.0);
+  let g = sample_offset(in.uv, -2.0, 2.0);
+  let h = sample_offset(in.uv, 0.0, 2.0);
+  let i = sample_offset(in.uv, 2.0, 2.0);
+  let j = sample_offset(in.uv, -1.0, -1.0);
+  let k = sample_offset(in.uv, 1.0, -1.0);
+  let l = sample_offset(in.uv, -1.0, 1.0);
+  let m = sample_offset(in.uv, 1.0, 1.0);
+
+  var color = e * 0.125;
+  color += (a + c + g + i) * 0.03125;
+  color += (b + d + f + h) * 0.0625;
+  color += (j + k + l + m) * 0.125;
+
+  if bloom.prefilter != 0u {
+    color = prefilter(color);
+  }
+
+  return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,16 @@
+#include "bloom.wgsl"
+
+// 3x3 tent filter, added onto the next larger mip (or the scene) by the
+// pipeline's blend state. The radius spreads the taps to widen the glow.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let r = bloom.radius;
+
+  var color = sample_offset(in.uv, 0.0, 0.0) * 4.0;
+  color += (sample_offset(in.uv, 0.0, -r) + sample_offset(in.uv, -r, 0.0) +
+            sample_offset(in.uv, r, 0.0) + sample_offset(in.uv, 0.0, r)) * 2.0;
+  color += sample_offset(in.uv, -r, -r) + sample_offset(in.uv, r, -r) +
+           sample_offset(in.uv, -r, r) + sample_offset(in.uv, r, r);
+
+  return vec4<f32>(color / 16.0, 1.0);
+}
@@ -0,0 +1,39 @@
+#include "fullscreen.wgsl"
+#include "ibl.wgsl"
+
+const SAMPLE_COUNT: u32 = 512u;
+
+fn geometry_schlick_ggx(n_dot_v: f32, roughness: f32) -> f32 {
+  let k = roughness * roughness / 2.0;
+  return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+// Scale and bias to F0 of the specular BRDF integrated over the hemisphere,
+// indexed by N.V along x and roughness along y.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let n_dot_v = max(in.uv.x, 0.0001);
+  let roughness = in.uv.y;
+  let v = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+  let normal = vec3<f32>(0.0, 0.0, 1.0);
+
+  var scale = 0.0;
+  var bias = 0.0;
+  for (var i = 0u; i < SAMPLE_COUNT; i++) {
+    let h = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, roughness);
+    let l = normalize(2.0 * dot(v, h) * h - v);
+    let n_dot_l = max(l.z, 0.0);
+    let n_dot_h = max(h.z, 0.0);
+    let v_dot_h = max(dot(v, h), 0.0);
+
+    if n_dot_l > 0.0 {
+      let g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+      let g_vis = g * v_dot_h / (n_dot_h * n_dot_v);
+      let fc = pow(1.0 - v_dot_h, 5.0);
+      scale += (1.0 - fc) * g_vis;
+      bias += fc * g_vis;
+    }
+  }
+
+  return vec4<f32>(scale / f32(SAMPLE_COUNT), bias / f32(SAMPLE_COUNT), 0.0, 1.0);
+}
@@ -0,0 +1,14 @@
+// Must match `camera::CameraUniform`. Define CAMERA_GROUP before including
+// this file to bind the camera somewhere other than group 0.
+#ifndef CAMERA_GROUP
+#define CAMERA_GROUP 0
+#endif
+
+struct CameraUniform {
+  view_proj: mat4x4<f32>,
+  inv_view_proj: mat4x4<f32>,
+  view_position: vec4<f32>,
+};
+
+@group(CAMERA_GROUP) @binding(0)
+var<uniform> camera: CameraUniform;
@@ -0,0 +1,37 @@
+#include "math.wgsl"
+
+// Renders one face of a cubemap per instance: draw three vertices with the
+// face index as the instance, into a 2D view of that face's layer.
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+  @location(1) @interpolate(flat) face: u32,
+};
+
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+  @builtin(instance_index) face: u32,
+) -> VertexOutput {
+  let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+  out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+  out.face = face;
+  return out;
+}
+
+// Direction through `uv` of a face, in the +X, -X, +Y, -Y, +Z, -Z layer order.
+fn face_direction(face: u32, uv: vec2<f32>) -> vec3<f32> {
+  let s = uv.x * 2.0 - 1.0;
+  let t = uv.y * 2.0 - 1.0;
+  switch face {
+    case 0u: { return vec3<f32>(1.0, -t, -s); }
+    case 1u: { return vec3<f32>(-1.0, -t, s); }
+    case 2u: { return vec3<f32>(s, 1.0, t); }
+    case 3u: { return vec3<f32>(s, -1.0, -t); }
+    case 4u: { return vec3<f32>(s, -t, 1.0); }
+    default: { return vec3<f32>(-s, -t, -1.0); }
+  }
+}
@@ -0,0 +1,56 @@
+// Image-based lighting. Bound alongside each material's textures, see
+// `environment::Environment`. Define ENVIRONMENT_GROUP before including this
+// file to bind it somewhere other than group 0.
+#ifndef ENVIRONMENT_GROUP
+#define ENVIRONMENT_GROUP 0
+#endif
+
+// Must match `environment::EnvironmentUniform`.
+struct EnvironmentSettings {
+  intensity: f32,
+  // Mip of the prefiltered map at roughness 1.
+  max_lod: f32,
+  enabled: u32,
+  _padding: u32,
+};
+
+@group(ENVIRONMENT_GROUP) @binding(2)
+var irradiance_map: texture_cube<f32>;
+@group(ENVIRONMENT_GROUP) @binding(3)
+var prefiltered_map: texture_cube<f32>;
+@group(ENVIRONMENT_GROUP) @binding(4)
+var brdf_lut: texture_2d<f32>;
+@group(ENVIRONMENT_GROUP) @binding(5)
+var environment_sampler: sampler;
+@group(ENVIRONMENT_GROUP) @binding(6)
+var<uniform> environment: EnvironmentSettings;
+
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+  return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// Diffuse and specular light reflected from the environment, using the split
+// sum approximation for the specular part.
+fn environment_lighting(
+  albedo: vec3<f32>,
+  normal: vec3<f32>,
+  view: vec3<f32>,
+  f0: vec3<f32>,
+  roughness: f32,
+  metallic: f32,
+) -> vec3<f32> {
+  let n_dot_v = max(dot(normal, view), 0.0);
+  let fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+  let diffuse_weight = (1.0 - fresnel) * (1.0 - metallic);
+
+  let irradiance = textureSample(irradiance_map, environment_sampler, normal).rgb;
+  let diffuse = irradiance * albedo;
+
+  let reflected = reflect(-view, normal);
+  let lod = roughness * environment.max_lod;
+  let prefiltered = textureSampleLevel(prefiltered_map, environment_sampler, reflected, lod).rgb;
+  let brdf = textureSample(brdf_lut, environment_sampler, vec2<f32>(n_dot_v, roughness)).rg;
+  let specular = prefiltered * (fresnel * brdf.x + brdf.y);
+
+  return (diffuse_weight * diffuse + specular) * environment.intensity;
+}
@@ -0,0 +1,30 @@
+#include "cube_face.wgsl"
+
+// Converts an equirectangular image to a cubemap. Float32 textures aren't
+// filterable without an extra feature, so the source is filtered by hand.
+@group(0) @binding(0)
+var equirect: texture_2d<f32>;
+
+fn load(texel: vec2<i32>, size: vec2<i32>) -> vec3<f32> {
+  let x = (texel.x % size.x + size.x) % size.x;
+  let y = clamp(texel.y, 0, size.y - 1);
+  return textureLoad(equirect, vec2<i32>(x, y), 0).rgb;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let direction = normalize(face_direction(in.face, in.uv));
+  let uv = vec2<f32>(
+    atan2(direction.z, direction.x) / (2.0 * PI) + 0.5,
+    acos(clamp(direction.y, -1.0, 1.0)) / PI,
+  );
+
+  let size = vec2<i32>(textureDimensions(equirect));
+  let position = uv * vec2<f32>(size) - 0.5;
+  let texel = vec2<i32>(floor(position));
+  let f = fract(position);
+
+  let top = mix(load(texel, size), load(texel + vec2<i32>(1, 0), size), f.x);
+  let bottom = mix(load(texel + vec2<i32>(0, 1), size), load(texel + vec2<i32>(1, 1), size), f.x);
+  return vec4<f32>(mix(top, bottom, f.y), 1.0);
+}
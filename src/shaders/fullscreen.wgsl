@@ -0,0 +1,17 @@
+// A single triangle covering the screen, drawn with `draw(0..3, 0..1)` and no
+// vertex buffers. `uv` has its origin in the top left, like texture
+// coordinates.
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+  out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+  return out;
+}
@@ -0,0 +1,51 @@
+#include "post.wgsl"
+
+const EDGE_THRESHOLD: f32 = 0.125;
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const SPAN_MAX: f32 = 8.0;
+const REDUCE_MUL: f32 = 0.125;
+const REDUCE_MIN: f32 = 0.0078125;
+
+// FXAA 3.11 console variant. Expects gamma encoded input.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let texel = post.texel_size;
+  let center = textureSample(source, source_sampler, in.uv);
+
+  let luma_nw = luma(textureSample(source, source_sampler, in.uv + vec2<f32>(-1.0, -1.0) * texel).rgb);
+  let luma_ne = luma(textureSample(source, source_sampler, in.uv + vec2<f32>(1.0, -1.0) * texel).rgb);
+  let luma_sw = luma(textureSample(source, source_sampler, in.uv + vec2<f32>(-1.0, 1.0) * texel).rgb);
+  let luma_se = luma(textureSample(source, source_sampler, in.uv + vec2<f32>(1.0, 1.0) * texel).rgb);
+  let luma_m = luma(center.rgb);
+
+  let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+  let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+  var direction = vec2<f32>(
+    -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+    (luma_nw + luma_sw) - (luma_ne + luma_se),
+  );
+  let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+  let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+  direction = clamp(direction * scale, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+  let rgb_a = 0.5 * (
+    textureSample(source, source_sampler, in.uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+    textureSample(source, source_sampler, in.uv + direction * (2.0 / 3.0 - 0.5)).rgb
+  );
+  let rgb_b = rgb_a * 0.5 + 0.25 * (
+    textureSample(source, source_sampler, in.uv + direction * -0.5).rgb +
+    textureSample(source, source_sampler, in.uv + direction * 0.5).rgb
+  );
+
+  let contrast = luma_max - luma_min;
+  if contrast < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD) {
+    return center;
+  }
+
+  let luma_b = luma(rgb_b);
+  if luma_b < luma_min || luma_b > luma_max {
+    return vec4<f32>(rgb_a, center.a);
+  }
+  return vec4<f32>(rgb_b, center.a);
+}
@@ -0,0 +1,120 @@
+#include "camera.wgsl"
+
+// Must match `grid::GridUniform`.
+struct GridSettings {
+  spacing: f32,
+  major_spacing: f32,
+  fade_distance: f32,
+  show_axes: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> grid: GridSettings;
+
+const MINOR_COLOR: vec3<f32> = vec3<f32>(0.35, 0.35, 0.35);
+const MAJOR_COLOR: vec3<f32> = vec3<f32>(0.6, 0.6, 0.6);
+const X_AXIS_COLOR: vec3<f32> = vec3<f32>(0.9, 0.2, 0.2);
+const Y_AXIS_COLOR: vec3<f32> = vec3<f32>(0.2, 0.9, 0.2);
+const Z_AXIS_COLOR: vec3<f32> = vec3<f32>(0.2, 0.4, 0.95);
+const MINOR_ALPHA: f32 = 0.4;
+const AXIS_WIDTH: f32 = 1.5;
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) ndc: vec2<f32>,
+};
+
+// A single triangle covering the screen.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+  let ndc = uv * 2.0 - 1.0;
+
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+  out.ndc = ndc;
+  return out;
+}
+
+fn unproject(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+  let position = camera.inv_view_proj * vec4<f32>(ndc, depth, 1.0);
+  return position.xyz / position.w;
+}
+
+fn depth_of(position: vec3<f32>) -> f32 {
+  let clip = camera.view_proj * vec4<f32>(position, 1.0);
+  return clip.z / clip.w;
+}
+
+// Antialiased coverage of the lines every `spacing` units, about a pixel wide.
+fn grid_lines(coord: vec2<f32>, spacing: f32) -> f32 {
+  let cell = coord / spacing;
+  let distance = abs(fract(cell - 0.5) - 0.5) / fwidth(cell);
+  return 1.0 - min(min(distance.x, distance.y), 1.0);
+}
+
+// Coverage of a line along `distance == 0`.
+fn axis_line(distance: f32, width: f32) -> f32 {
+  return 1.0 - min(abs(distance) / (width * AXIS_WIDTH), 1.0);
+}
+
+struct FragmentOutput {
+  @location(0) color: vec4<f32>,
+  @builtin(frag_depth) depth: f32,
+};
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+  let near = unproject(in.ndc, 0.0);
+  let ray = unproject(in.ndc, 1.0) - near;
+
+  // Where the view ray crosses the ground plane, as a fraction of the way
+  // from the near to the far plane.
+  let t = -near.y / ray.y;
+  let position = near + ray * t;
+
+  // Derivatives have to be taken before branching on the result.
+  let minor = grid_lines(position.xz, grid.spacing);
+  let major = grid_lines(position.xz, grid.major_spacing);
+  let x_axis = axis_line(position.z, fwidth(position.z));
+  let z_axis = axis_line(position.x, fwidth(position.x));
+
+  // The point on the view ray closest to the Y axis.
+  let s = -dot(near.xz, ray.xz) / dot(ray.xz, ray.xz);
+  let y_position = near + ray * s;
+  let y_axis = axis_line(length(y_position.xz), length(fwidth(y_position)));
+
+  var color = vec4<f32>(0.0);
+  var depth = 1.0;
+
+  if t > 0.0 && t < 1.0 {
+    let distance = length(position - camera.view_position.xyz);
+    let fade = 1.0 - smoothstep(grid.fade_distance * 0.5, grid.fade_distance, distance);
+
+    color = vec4<f32>(MINOR_COLOR, minor * MINOR_ALPHA);
+    color = mix(color, vec4<f32>(MAJOR_COLOR, 1.0), major);
+    if grid.show_axes != 0u {
+      color = mix(color, vec4<f32>(X_AXIS_COLOR, 1.0), x_axis);
+      color = mix(color, vec4<f32>(Z_AXIS_COLOR, 1.0), z_axis);
+    }
+    color.a *= fade;
+    depth = depth_of(position);
+  }
+
+  if grid.show_axes != 0u && s > 0.0 && s < 1.0 && y_axis > 0.0 {
+    let y_depth = depth_of(y_position);
+    if color.a <= 0.0 || y_depth < depth {
+      color = vec4<f32>(Y_AXIS_COLOR, y_axis);
+      depth = y_depth;
+    }
+  }
+
+  if color.a <= 0.0 {
+    discard;
+  }
+
+  var out: FragmentOutput;
+  out.color = color;
+  out.depth = depth;
+  return out;
+}
@@ -0,0 +1,33 @@
+// Must match `instance::InstanceRaw::desc`.
+const FLAG_SELECTED: u32 = 1u;
+
+struct InstanceInput {
+  @location(5) model_matrix_0: vec4<f32>,
+  @location(6) model_matrix_1: vec4<f32>,
+  @location(7) model_matrix_2: vec4<f32>,
+  @location(8) model_matrix_3: vec4<f32>,
+  @location(9) color: vec4<f32>,
+  @location(10) flags: u32,
+  @location(11) id: u32,
+  @location(12) normal_matrix_0: vec3<f32>,
+  @location(13) normal_matrix_1: vec3<f32>,
+  @location(14) normal_matrix_2: vec3<f32>,
+}
+
+fn instance_model_matrix(instance: InstanceInput) -> mat4x4<f32> {
+  return mat4x4<f32>(
+    instance.model_matrix_0,
+    instance.model_matrix_1,
+    instance.model_matrix_2,
+    instance.model_matrix_3,
+  );
+}
+
+// The inverse transpose of the model matrix, for normals.
+fn instance_normal_matrix(instance: InstanceInput) -> mat3x3<f32> {
+  return mat3x3<f32>(
+    instance.normal_matrix_0,
+    instance.normal_matrix_1,
+    instance.normal_matrix_2,
+  );
+}
@@ -0,0 +1,35 @@
+#include "cube_face.wgsl"
+
+@group(0) @binding(0)
+var environment: texture_cube<f32>;
+@group(0) @binding(1)
+var environment_sampler: sampler;
+
+const SAMPLE_DELTA: f32 = 0.025;
+
+// Cosine-weighted average of the environment over the hemisphere around each
+// direction, so diffuse ambient light is a single lookup.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let normal = normalize(face_direction(in.face, in.uv));
+  var up = vec3<f32>(0.0, 1.0, 0.0);
+  if abs(normal.y) > 0.999 {
+    up = vec3<f32>(0.0, 0.0, 1.0);
+  }
+  let right = normalize(cross(up, normal));
+  up = cross(normal, right);
+
+  var irradiance = vec3<f32>(0.0);
+  var count = 0.0;
+  for (var phi = 0.0; phi < 2.0 * PI; phi += SAMPLE_DELTA) {
+    for (var theta = 0.0; theta < 0.5 * PI; theta += SAMPLE_DELTA) {
+      let tangent = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+      let direction = tangent.x * right + tangent.y * up + tangent.z * normal;
+      let color = textureSampleLevel(environment, environment_sampler, direction, 0.0).rgb;
+      irradiance += color * cos(theta) * sin(theta);
+      count += 1.0;
+    }
+  }
+
+  return vec4<f32>(PI * irradiance / count, 1.0);
+}
@@ -0,0 +1,18 @@
+// Must match `shadow::LightUniform`. Define LIGHT_GROUP before including this
+// file to bind the light somewhere other than group 3.
+#ifndef LIGHT_GROUP
+#define LIGHT_GROUP 3
+#endif
+
+struct Light {
+  view_proj: mat4x4<f32>,
+  direction: vec4<f32>,
+  color: vec4<f32>,
+  shadow_bias: f32,
+  shadow_texel_size: f32,
+  shadows_enabled: u32,
+  _padding: u32,
+};
+
+@group(LIGHT_GROUP) @binding(0)
+var<uniform> light: Light;
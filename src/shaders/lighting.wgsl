@@ -0,0 +1,118 @@
+#include "camera.wgsl"
+#include "environment.wgsl"
+#include "light.wgsl"
+#include "lights.wgsl"
+#include "pbr.wgsl"
+
+@group(LIGHT_GROUP) @binding(1)
+var shadow_map: texture_depth_2d;
+@group(LIGHT_GROUP) @binding(2)
+var shadow_sampler: sampler_comparison;
+
+// Ambient light used when image-based lighting is off.
+const AMBIENT_STRENGTH: f32 = 0.3;
+
+struct Surface {
+  albedo: vec3<f32>,
+  normal: vec3<f32>,
+  world_position: vec3<f32>,
+  metallic: f32,
+  roughness: f32,
+  occlusion: f32,
+  emissive: vec3<f32>,
+};
+
+// Fraction of light reaching `world_position`, averaged over a 3x3 block of
+// shadow map texels (percentage-closer filtering).
+fn shadow(world_position: vec3<f32>) -> f32 {
+  if light.shadows_enabled == 0u {
+    return 1.0;
+  }
+
+  let clip = light.view_proj * vec4<f32>(world_position, 1.0);
+  let ndc = clip.xyz / clip.w;
+  let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+  if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0 {
+    return 1.0;
+  }
+
+  var lit = 0.0;
+  for (var x = -1; x <= 1; x++) {
+    for (var y = -1; y <= 1; y++) {
+      let offset = vec2<f32>(f32(x), f32(y)) * light.shadow_texel_size;
+      lit += textureSampleCompareLevel(
+        shadow_map,
+        shadow_sampler,
+        uv + offset,
+        ndc.z - light.shadow_bias,
+      );
+    }
+  }
+
+  return lit / 9.0;
+}
+
+// Windowed inverse square falloff, reaching zero at `range`.
+fn attenuation(distance: f32, range: f32) -> f32 {
+  let window = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+  return window * window / max(distance * distance, 1e-4);
+}
+
+fn punctual_lighting(surface: Surface, normal: vec3<f32>, view: vec3<f32>) -> vec3<f32> {
+  var color = vec3<f32>(0.0);
+  for (var i = 0u; i < min(lights.count, u32(MAX_LIGHTS)); i++) {
+    let punctual = lights.lights[i];
+    var to_light = -normalize(punctual.direction.xyz);
+    var radiance = punctual.color * punctual.intensity;
+    if punctual.kind != LIGHT_DIRECTIONAL {
+      let offset = punctual.position.xyz - surface.world_position;
+      to_light = normalize(offset);
+      radiance *= attenuation(length(offset), punctual.range);
+    }
+    if punctual.kind == LIGHT_SPOT {
+      let cos_angle = dot(-to_light, normalize(punctual.direction.xyz));
+      radiance *= smoothstep(punctual.cos_outer, punctual.cos_inner, cos_angle);
+    }
+    color += cook_torrance(
+      normal,
+      view,
+      to_light,
+      radiance,
+      surface.albedo,
+      surface.metallic,
+      surface.roughness,
+    );
+  }
+  return color;
+}
+
+fn apply_lighting(surface: Surface) -> vec3<f32> {
+  let normal = normalize(surface.normal);
+  let view = normalize(camera.view_position.xyz - surface.world_position);
+
+  let sun = cook_torrance(
+    normal,
+    view,
+    -normalize(light.direction.xyz),
+    light.color.rgb,
+    surface.albedo,
+    surface.metallic,
+    surface.roughness,
+  );
+  let direct = sun * shadow(surface.world_position) + punctual_lighting(surface, normal, view);
+
+  var ambient = surface.albedo * AMBIENT_STRENGTH;
+  if environment.enabled != 0u {
+    let f0 = mix(vec3<f32>(0.04), surface.albedo, surface.metallic);
+    ambient = environment_lighting(
+      surface.albedo,
+      normal,
+      view,
+      f0,
+      surface.roughness,
+      surface.metallic,
+    );
+  }
+
+  return direct + ambient * surface.occlusion + surface.emissive;
+}
@@ -0,0 +1,31 @@
+#include "light.wgsl"
+
+// Must match `lights::LightsUniform`. Bound next to the shadow-casting light.
+#define MAX_LIGHTS 16
+#define LIGHT_DIRECTIONAL 0u
+#define LIGHT_POINT 1u
+#define LIGHT_SPOT 2u
+
+struct PunctualLight {
+  position: vec4<f32>,
+  // Direction the light travels in, for directional and spot lights.
+  direction: vec4<f32>,
+  color: vec3<f32>,
+  intensity: f32,
+  kind: u32,
+  // Distance at which a point or spot light fades out completely.
+  range: f32,
+  // Cosines of the spot cone's half angles, at full and at zero intensity.
+  cos_inner: f32,
+  cos_outer: f32,
+};
+
+struct Lights {
+  count: u32,
+  _padding0: u32,
+  _padding1: vec2<u32>,
+  lights: array<PunctualLight, MAX_LIGHTS>,
+};
+
+@group(LIGHT_GROUP) @binding(3)
+var<uniform> lights: Lights;
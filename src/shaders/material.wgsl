@@ -0,0 +1,66 @@
+// Must match `model::MaterialUniform`. Define MATERIAL_GROUP before including
+// this file to bind the material somewhere other than group 0.
+#ifndef MATERIAL_GROUP
+#define MATERIAL_GROUP 0
+#endif
+
+#define MATERIAL_BASE_COLOR_TEXTURE 1u
+#define MATERIAL_METALLIC_ROUGHNESS_TEXTURE 2u
+#define MATERIAL_EMISSIVE_TEXTURE 4u
+#define MATERIAL_OCCLUSION_TEXTURE 8u
+
+struct Material {
+  base_color: vec4<f32>,
+  emissive: vec3<f32>,
+  metallic: f32,
+  roughness: f32,
+  occlusion_strength: f32,
+  // Which of the textures are present, uniforms being used for the others.
+  textures: u32,
+  // Fragments with a lower alpha are discarded, zero unless alpha tested.
+  alpha_cutoff: f32,
+};
+
+@group(MATERIAL_GROUP) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(MATERIAL_GROUP) @binding(1)
+var s_diffuse: sampler;
+@group(MATERIAL_GROUP) @binding(7)
+var t_metallic_roughness: texture_2d<f32>;
+@group(MATERIAL_GROUP) @binding(8)
+var t_emissive: texture_2d<f32>;
+@group(MATERIAL_GROUP) @binding(9)
+var t_occlusion: texture_2d<f32>;
+@group(MATERIAL_GROUP) @binding(10)
+var<uniform> material: Material;
+
+fn has_texture(flag: u32) -> bool {
+  return (material.textures & flag) != 0u;
+}
+
+// The textures are sampled even when absent, since sampling in non-uniform
+// control flow is not allowed, and the placeholder bound instead is ignored.
+
+// The factor's alpha still applies to a texture, so a textured material can
+// be faded out.
+fn material_base_color(uv: vec2<f32>) -> vec4<f32> {
+  let sampled = textureSample(t_diffuse, s_diffuse, uv) * vec4<f32>(1.0, 1.0, 1.0, material.base_color.a);
+  return select(material.base_color, sampled, has_texture(MATERIAL_BASE_COLOR_TEXTURE));
+}
+
+// Metallic in x, roughness in y.
+fn material_metallic_roughness(uv: vec2<f32>) -> vec2<f32> {
+  let sampled = textureSample(t_metallic_roughness, s_diffuse, uv).bg;
+  let factors = vec2<f32>(material.metallic, material.roughness);
+  return select(factors, sampled, has_texture(MATERIAL_METALLIC_ROUGHNESS_TEXTURE));
+}
+
+fn material_emissive(uv: vec2<f32>) -> vec3<f32> {
+  let sampled = textureSample(t_emissive, s_diffuse, uv).rgb;
+  return select(material.emissive, sampled, has_texture(MATERIAL_EMISSIVE_TEXTURE));
+}
+
+fn material_occlusion(uv: vec2<f32>) -> f32 {
+  let sampled = mix(1.0, textureSample(t_occlusion, s_diffuse, uv).r, material.occlusion_strength);
+  return select(1.0, sampled, has_texture(MATERIAL_OCCLUSION_TEXTURE));
+}
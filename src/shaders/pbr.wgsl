@@ -0,0 +1,59 @@
+#include "math.wgsl"
+
+// Cook-Torrance microfacet BRDF with the GGX distribution, Smith-Schlick
+// geometry term and Schlick's Fresnel approximation.
+
+// Below this, highlights from punctual lights collapse to a single pixel.
+const MIN_ROUGHNESS: f32 = 0.04;
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+  let a = roughness * roughness;
+  let a2 = a * a;
+  let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+  return a2 / (PI * d * d);
+}
+
+fn geometry_schlick_ggx(n_dot_x: f32, roughness: f32) -> f32 {
+  let r = roughness + 1.0;
+  let k = r * r / 8.0;
+  return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+  return geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+  return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Light reflected towards `view` from `radiance` arriving along `to_light`.
+// All vectors are normalized and point away from the surface.
+fn cook_torrance(
+  normal: vec3<f32>,
+  view: vec3<f32>,
+  to_light: vec3<f32>,
+  radiance: vec3<f32>,
+  albedo: vec3<f32>,
+  metallic: f32,
+  roughness_in: f32,
+) -> vec3<f32> {
+  let n_dot_l = max(dot(normal, to_light), 0.0);
+  if n_dot_l <= 0.0 {
+    return vec3<f32>(0.0);
+  }
+
+  let roughness = max(roughness_in, MIN_ROUGHNESS);
+  let half_vector = normalize(view + to_light);
+  let n_dot_v = max(dot(normal, view), 1e-4);
+  let n_dot_h = max(dot(normal, half_vector), 0.0);
+  let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+
+  let fresnel = fresnel_schlick(max(dot(half_vector, view), 0.0), f0);
+  let d = distribution_ggx(n_dot_h, roughness);
+  let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+  let specular = fresnel * d * g / (4.0 * n_dot_v * n_dot_l);
+  let diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;
+
+  return (diffuse + specular) * radiance * n_dot_l;
+}
@@ -0,0 +1,24 @@
+#include "fullscreen.wgsl"
+
+// Shared by the post-processing passes, each sampling the previous pass. Must
+// match `postprocess::PostUniform`.
+struct PostSettings {
+  texel_size: vec2<f32>,
+  exposure: f32,
+  tone_mapping: u32,
+  gamma: f32,
+  vignette_strength: f32,
+  vignette_radius: f32,
+  _padding: u32,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostSettings;
+
+fn luma(color: vec3<f32>) -> f32 {
+  return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
@@ -0,0 +1,59 @@
+#include "cube_face.wgsl"
+#include "ibl.wgsl"
+#include "pbr.wgsl"
+
+// Must match `environment::PrefilterUniform`.
+struct PrefilterSettings {
+  roughness: f32,
+  // Of the top mip of `environment`.
+  source_size: f32,
+  max_lod: f32,
+  _padding: f32,
+};
+
+@group(0) @binding(0)
+var environment: texture_cube<f32>;
+@group(0) @binding(1)
+var environment_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> prefilter: PrefilterSettings;
+
+const SAMPLE_COUNT: u32 = 1024u;
+
+// The environment convolved with the GGX lobe of one roughness per mip, for
+// the split-sum specular approximation. Assumes the view is along the normal.
+// At roughness 0 it copies, which is also how the source mips are made.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let normal = normalize(face_direction(in.face, in.uv));
+
+  if prefilter.roughness == 0.0 {
+    return vec4<f32>(textureSampleLevel(environment, environment_sampler, normal, 0.0).rgb, 1.0);
+  }
+
+  let texel_solid_angle = 4.0 * PI / (6.0 * prefilter.source_size * prefilter.source_size);
+
+  var color = vec3<f32>(0.0);
+  var weight = 0.0;
+  for (var i = 0u; i < SAMPLE_COUNT; i++) {
+    let h = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, prefilter.roughness);
+    let l = normalize(2.0 * dot(normal, h) * h - normal);
+    let n_dot_l = dot(normal, l);
+    if n_dot_l > 0.0 {
+      // Filtered importance sampling: each sample reads the mip whose texels
+      // cover the solid angle it stands for, instead of a single bright texel.
+      // With the view along the normal, the pdf of `l` is D / 4.
+      let pdf = distribution_ggx(max(dot(normal, h), 0.0), prefilter.roughness) / 4.0;
+      let sample_solid_angle = 1.0 / (f32(SAMPLE_COUNT) * pdf + 0.0001);
+      let lod = clamp(
+        0.5 * log2(sample_solid_angle / texel_solid_angle) + 1.0,
+        0.0,
+        prefilter.max_lod,
+      );
+      color += textureSampleLevel(environment, environment_sampler, l, lod).rgb * n_dot_l;
+      weight += n_dot_l;
+    }
+  }
+
+  return vec4<f32>(color / max(weight, 0.0001), 1.0);
+}
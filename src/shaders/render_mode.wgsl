@@ -0,0 +1,48 @@
+// Must match `render_mode::RenderSettingsUniform` and `RenderMode::shader_mode`.
+// The modes are defines because naga only accepts literals as switch cases.
+#define MODE_SHADED 0u
+#define MODE_UNLIT 1u
+#define MODE_NORMALS 2u
+#define MODE_UV_CHECKER 3u
+#define MODE_DEPTH 4u
+#define MODE_MESH_COLORS 5u
+
+struct RenderSettings {
+  mode: u32,
+  mesh: u32,
+  znear: f32,
+  zfar: f32,
+};
+
+@group(2) @binding(0)
+var<uniform> settings: RenderSettings;
+
+fn hash(value: u32) -> u32 {
+  var h = value * 747796405u + 2891336453u;
+  h = ((h >> ((h >> 28u) + 4u)) ^ h) * 277803737u;
+  return (h >> 22u) ^ h;
+}
+
+fn random_color(seed: u32) -> vec3<f32> {
+  let h = hash(seed);
+  return vec3<f32>(
+    f32(h & 255u),
+    f32((h >> 8u) & 255u),
+    f32((h >> 16u) & 255u),
+  ) / 255.0;
+}
+
+fn uv_checker(uv: vec2<f32>) -> vec3<f32> {
+  let cell = floor(uv * 8.0);
+  let parity = (cell.x + cell.y) - 2.0 * floor((cell.x + cell.y) / 2.0);
+  return mix(vec3<f32>(uv, 0.0), vec3<f32>(1.0), 0.5 * parity);
+}
+
+// Turns a depth buffer value back into a distance from the camera, scaled to
+// 0 at the near plane and 1 at the far plane.
+fn linear_depth(depth: f32) -> f32 {
+  let n = settings.znear;
+  let f = settings.zfar;
+  let z = n * f / (f - depth * (f - n));
+  return (z - n) / (f - n);
+}
@@ -0,0 +1,92 @@
+#define CAMERA_GROUP 1
+
+#include "camera.wgsl"
+#include "instance.wgsl"
+#include "lighting.wgsl"
+#include "material.wgsl"
+#include "render_mode.wgsl"
+
+struct ModelInput {
+  @location(0) position: vec3<f32>,
+  @location(1) tex_coords: vec2<f32>,
+  @location(2) normal: vec3<f32>,
+}
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) tex_coords: vec2<f32>,
+  @location(1) color: vec4<f32>,
+  @location(2) @interpolate(flat) flags: u32,
+  @location(3) world_normal: vec3<f32>,
+  @location(4) @interpolate(flat) instance: u32,
+  @location(5) world_position: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+  model: ModelInput,
+  instance: InstanceInput,
+) -> VertexOutput {
+  let model_matrix = instance_model_matrix(instance);
+
+  var out: VertexOutput;
+
+  out.tex_coords = model.tex_coords;
+  out.color = instance.color;
+  out.flags = instance.flags;
+  out.instance = instance.id;
+  out.world_normal = instance_normal_matrix(instance) * model.normal;
+  let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+  out.world_position = world_position.xyz;
+  out.clip_position = camera.view_proj * world_position;
+
+  return out;
+}
+
+
+const SELECTION_COLOR: vec3<f32> = vec3<f32>(1.0, 0.6, 0.1);
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  var color = material_base_color(in.tex_coords) * in.color;
+  let metallic_roughness = material_metallic_roughness(in.tex_coords);
+  let occlusion = material_occlusion(in.tex_coords);
+  let emissive = material_emissive(in.tex_coords);
+  // After the samples, which need every fragment of the quad alive.
+  if color.a < material.alpha_cutoff {
+    discard;
+  }
+
+  switch settings.mode {
+    case MODE_UNLIT: {}
+    case MODE_NORMALS: {
+      color = vec4<f32>(normalize(in.world_normal) * 0.5 + 0.5, 1.0);
+    }
+    case MODE_UV_CHECKER: {
+      color = vec4<f32>(uv_checker(in.tex_coords), 1.0);
+    }
+    case MODE_DEPTH: {
+      color = vec4<f32>(vec3<f32>(linear_depth(in.clip_position.z)), 1.0);
+    }
+    case MODE_MESH_COLORS: {
+      color = vec4<f32>(random_color(hash(settings.mesh) ^ in.instance), 1.0);
+    }
+    default: {
+      var surface: Surface;
+      surface.albedo = color.rgb;
+      surface.normal = in.world_normal;
+      surface.world_position = in.world_position;
+      surface.metallic = metallic_roughness.x;
+      surface.roughness = metallic_roughness.y;
+      surface.occlusion = occlusion;
+      surface.emissive = emissive;
+      color = vec4<f32>(apply_lighting(surface), color.a);
+    }
+  }
+
+  if (in.flags & FLAG_SELECTED) != 0u {
+    return vec4<f32>(mix(color.rgb, SELECTION_COLOR, 0.4), color.a);
+  }
+
+  return color;
+}
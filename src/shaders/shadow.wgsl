@@ -0,0 +1,18 @@
+#define LIGHT_GROUP 0
+
+#include "instance.wgsl"
+#include "light.wgsl"
+
+struct ModelInput {
+  @location(0) position: vec3<f32>,
+}
+
+// Depth only, there is no fragment stage.
+@vertex
+fn vs_main(
+  model: ModelInput,
+  instance: InstanceInput,
+) -> @builtin(position) vec4<f32> {
+  let model_matrix = instance_model_matrix(instance);
+  return light.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+}
@@ -0,0 +1,31 @@
+#include "camera.wgsl"
+
+@group(1) @binding(0)
+var sky: texture_cube<f32>;
+@group(1) @binding(1)
+var sky_sampler: sampler;
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) ndc: vec2<f32>,
+};
+
+// A single triangle covering the screen at the far plane, so it only shows
+// where nothing else was drawn.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+  let ndc = uv * 2.0 - 1.0;
+
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+  out.ndc = ndc;
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let far = camera.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+  let direction = normalize(far.xyz / far.w - camera.view_position.xyz);
+  return vec4<f32>(textureSample(sky, sky_sampler, direction).rgb, 1.0);
+}
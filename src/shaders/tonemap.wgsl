@@ -0,0 +1,40 @@
+#include "post.wgsl"
+
+// Must match `postprocess::ToneMapping`.
+#define TONE_MAPPING_NONE 0u
+#define TONE_MAPPING_REINHARD 1u
+#define TONE_MAPPING_ACES 2u
+
+// Narkowicz's fit of the ACES filmic curve.
+fn aces(color: vec3<f32>) -> vec3<f32> {
+  let a = 2.51;
+  let b = 0.03;
+  let c = 2.43;
+  let d = 0.59;
+  let e = 0.14;
+  return saturate((color * (a * color + b)) / (color * (c * color + d) + e));
+}
+
+// Resolves the HDR scene: exposure, tone mapping and gamma encoding.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  var color = textureSample(source, source_sampler, in.uv).rgb * post.exposure;
+
+  switch post.tone_mapping {
+    case TONE_MAPPING_REINHARD: {
+      color = color / (color + 1.0);
+    }
+    case TONE_MAPPING_ACES: {
+      color = aces(color);
+    }
+    default: {
+      color = saturate(color);
+    }
+  }
+
+  if post.gamma > 0.0 {
+    color = pow(color, vec3<f32>(1.0 / post.gamma));
+  }
+
+  return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,9 @@
+#include "post.wgsl"
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let color = textureSample(source, source_sampler, in.uv);
+  let distance = length(in.uv - 0.5) * 1.41421356;
+  let vignette = 1.0 - post.vignette_strength * smoothstep(post.vignette_radius, 1.0, distance);
+  return vec4<f32>(color.rgb * vignette, color.a);
+}
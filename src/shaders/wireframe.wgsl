@@ -0,0 +1,51 @@
+#include "camera.wgsl"
+#include "instance.wgsl"
+
+const WIRE_COLOR: vec3<f32> = vec3<f32>(0.05, 0.05, 0.05);
+const WIRE_WIDTH: f32 = 1.0;
+// Pulls the lines slightly towards the camera so they win the depth test
+// against the surface they were drawn from.
+const DEPTH_OFFSET: f32 = 0.0005;
+
+struct WireframeInput {
+  @location(0) position: vec3<f32>,
+}
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) barycentric: vec3<f32>,
+};
+
+// The wireframe buffers are not indexed, so every three vertices form a
+// triangle and the vertex index gives the corner.
+@vertex
+fn vs_main(
+  vertex: WireframeInput,
+  instance: InstanceInput,
+  @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+  let model_matrix = instance_model_matrix(instance);
+
+  var out: VertexOutput;
+  out.clip_position = camera.view_proj * model_matrix * vec4<f32>(vertex.position, 1.0);
+  out.clip_position.z -= DEPTH_OFFSET * out.clip_position.w;
+
+  let corner = vertex_index % 3u;
+  out.barycentric = vec3<f32>(f32(corner == 0u), f32(corner == 1u), f32(corner == 2u));
+
+  return out;
+}
+
+// With `PolygonMode::Line` every fragment lies on an edge already; the
+// barycentric test is what draws the edges when only filled polygons are
+// supported.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let width = fwidth(in.barycentric) * WIRE_WIDTH;
+  let edge = step(in.barycentric, width);
+  if max(edge.x, max(edge.y, edge.z)) == 0.0 {
+    discard;
+  }
+
+  return vec4<f32>(WIRE_COLOR, 1.0);
+}
@@ -0,0 +1,414 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector3};
+
+use crate::{
+    camera::OPENGL_TO_WGPU_MATRIX,
+    instance::InstanceRaw,
+    lights::Lights,
+    model::{self, Vertex},
+    render_queue::Draw,
+    shaders::ShaderId,
+    texture,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Direction the light travels in, from the light towards the scene.
+    pub light_direction: Vector3<f32>,
+    pub resolution: u32,
+    /// Subtracted from the receiver's depth before comparing, against acne.
+    pub bias: f32,
+    /// Half the size of the square area around the origin that casts shadows.
+    pub extent: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            light_direction: Vector3::new(-0.4, -1.0, -0.3),
+            resolution: 2048,
+            bias: 0.002,
+            extent: 20.0,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub const RESOLUTIONS: [u32; 4] = [512, 1024, 2048, 4096];
+
+    /// Orthographic projection covering `extent` around the origin, looking
+    /// along the light direction.
+    pub fn light_view_proj(&self) -> Matrix4<f32> {
+        let direction = if self.light_direction.is_zero() {
+            -Vector3::unit_y()
+        } else {
+            self.light_direction.normalize()
+        };
+        let up = if direction.y.abs() > 0.99 {
+            Vector3::unit_z()
+        } else {
+            Vector3::unit_y()
+        };
+
+        let distance = self.extent * 2.0;
+        let eye = Point3::origin() - direction * distance;
+        let view = Matrix4::look_at_rh(eye, Point3::origin(), up);
+        let proj = cgmath::ortho(
+            -self.extent,
+            self.extent,
+            -self.extent,
+            self.extent,
+            0.1,
+            distance * 2.0,
+        );
+
+        OPENGL_TO_WGPU_MATRIX * proj * view
+    }
+}
+
+/// Radiance of the light. A white surface facing it reflects about as much as
+/// it did before the BRDF divided the diffuse term by pi.
+const LIGHT_COLOR: [f32; 4] = [3.0, 3.0, 3.0, 1.0];
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct LightUniform {
+    view_proj: [[f32; 4]; 4],
+    direction: [f32; 4],
+    color: [f32; 4],
+    shadow_bias: f32,
+    shadow_texel_size: f32,
+    shadows_enabled: u32,
+    _padding: u32,
+}
+
+impl From<ShadowSettings> for LightUniform {
+    fn from(settings: ShadowSettings) -> Self {
+        Self {
+            view_proj: settings.light_view_proj().into(),
+            direction: settings.light_direction.extend(0.0).into(),
+            color: LIGHT_COLOR,
+            shadow_bias: settings.bias,
+            shadow_texel_size: 1.0 / settings.resolution as f32,
+            shadows_enabled: settings.enabled as u32,
+            _padding: 0,
+        }
+    }
+}
+
+/// The directional light and its shadow map. The light bind group is used
+/// while rendering the map, the lighting bind group, which also holds the map
+/// and the other lights, by the model pass.
+pub struct Shadows {
+    pub settings: ShadowSettings,
+    uploaded: Option<ShadowSettings>,
+    map: texture::Texture,
+    buffer: wgpu::Buffer,
+    light_bind_group: wgpu::BindGroup,
+    lighting_bind_group_layout: wgpu::BindGroupLayout,
+    lighting_bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Shadows {
+    pub fn new(device: &wgpu::Device, lights: &Lights) -> Self {
+        let settings = ShadowSettings::default();
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Buffer"),
+            size: std::mem::size_of::<LightUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let light_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let light_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("light_bind_group_layout"),
+                entries: &[light_entry],
+            });
+
+        let lighting_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("lighting_bind_group_layout"),
+                entries: &[
+                    light_entry,
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Depth,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light_bind_group"),
+            layout: &light_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        let map = create_shadow_map(device, settings.resolution);
+        let lighting_bind_group =
+            create_lighting_bind_group(device, &lighting_bind_group_layout, &buffer, &map, lights);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&light_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_shadow_pipeline(
+            device,
+            &pipeline_layout,
+            ShaderId::Shadow.descriptor(ShaderId::Shadow.embedded()),
+        );
+
+        Self {
+            settings,
+            uploaded: None,
+            map,
+            buffer,
+            light_bind_group,
+            lighting_bind_group_layout,
+            lighting_bind_group,
+            pipeline_layout,
+            pipeline,
+        }
+    }
+
+    pub fn lighting_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.lighting_bind_group_layout
+    }
+
+    pub fn lighting_bind_group(&self) -> &wgpu::BindGroup {
+        &self.lighting_bind_group
+    }
+
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        shader: wgpu::ShaderModuleDescriptor,
+    ) -> wgpu::RenderPipeline {
+        create_shadow_pipeline(device, &self.pipeline_layout, shader)
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: wgpu::RenderPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    /// Recreates the map when the resolution changed and uploads the light.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, lights: &Lights) {
+        if self.uploaded == Some(self.settings) {
+            return;
+        }
+
+        if self.map.texture.width() != self.settings.resolution {
+            self.map = create_shadow_map(device, self.settings.resolution);
+            self.lighting_bind_group = create_lighting_bind_group(
+                device,
+                &self.lighting_bind_group_layout,
+                &self.buffer,
+                &self.map,
+                lights,
+            );
+        }
+
+        let uniform = LightUniform::from(self.settings);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+        self.uploaded = Some(self.settings);
+    }
+
+    /// Renders `draws` of the scene's models into the shadow map, which is
+    /// only cleared without an `instance_buffer`. Does nothing when shadows
+    /// are disabled, since the map is not sampled then.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        models: &[model::Model],
+        instance_buffer: Option<wgpu::BufferSlice>,
+        draws: &[Draw],
+    ) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow Render Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.map.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        let Some(instance_buffer) = instance_buffer.filter(|_| !draws.is_empty()) else {
+            return;
+        };
+
+        shadow_pass.set_pipeline(&self.pipeline);
+        shadow_pass.set_bind_group(0, &self.light_bind_group, &[]);
+        shadow_pass.set_vertex_buffer(1, instance_buffer);
+        for draw in draws {
+            let mesh = &models[draw.model].meshes[draw.mesh];
+            shadow_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            shadow_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            shadow_pass.draw_indexed(0..mesh.num_elements, 0, draw.instances.clone());
+        }
+    }
+}
+
+fn create_shadow_map(device: &wgpu::Device, resolution: u32) -> texture::Texture {
+    texture::Texture::create_depth_texture_with_size(device, resolution, resolution, "shadow_map")
+}
+
+fn create_lighting_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+    map: &texture::Texture,
+    lights: &Lights,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("lighting_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&map.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&map.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: lights.buffer().as_entire_binding(),
+            },
+        ],
+    })
+}
+
+// Depth only, so it has no fragment stage and doesn't go through
+// `create_render_pipeline`.
+fn create_shadow_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(shader);
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadow Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[model::ModelVertex::desc(), InstanceRaw::desc()],
+        },
+        fragment: None,
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            // Scales with the surface slope, on top of the bias in the shader.
+            bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LightUniform;
+    use crate::instance::InstanceRaw;
+    use crate::layout;
+    use crate::model::{ModelVertex, Vertex};
+    use crate::shaders::ShaderId;
+
+    #[test]
+    fn uniform_matches_wgsl() {
+        layout::assert_struct_fields!(
+            LightUniform,
+            "Light",
+            [
+                view_proj,
+                direction,
+                color,
+                shadow_bias,
+                shadow_texel_size,
+                shadows_enabled,
+                _padding,
+            ]
+        );
+    }
+
+    #[test]
+    fn shadow_pass_inputs_match() {
+        layout::assert_vertex_inputs(
+            ShaderId::Shadow,
+            "vs_main",
+            &[ModelVertex::desc(), InstanceRaw::desc()],
+        );
+    }
+}
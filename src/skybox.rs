@@ -0,0 +1,464 @@
+use std::f32::consts::PI;
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::{
+    event::AppStatus,
+    renderer::{create_render_pipeline, PipelineState},
+    resources,
+    shaders::ShaderId,
+    texture,
+};
+
+/// Looked up in `res/` at startup, the equirectangular image first.
+const SKY_HDR: &str = "sky.hdr";
+const SKY_FACES: [&str; 6] = [
+    "sky/px.png",
+    "sky/nx.png",
+    "sky/py.png",
+    "sky/ny.png",
+    "sky/pz.png",
+    "sky/nz.png",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundMode {
+    Color,
+    Skybox,
+}
+
+impl BackgroundMode {
+    pub const ALL: [BackgroundMode; 2] = [Self::Color, Self::Skybox];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Color => "Solid color",
+            Self::Skybox => "Skybox",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackgroundSettings {
+    pub mode: BackgroundMode,
+    /// Linear RGB.
+    pub color: [f32; 3],
+    /// Generates a daylight sky when none is found in `res/`, instead of the
+    /// flat grey one.
+    pub procedural_sky: bool,
+}
+
+impl Default for BackgroundSettings {
+    fn default() -> Self {
+        Self {
+            mode: BackgroundMode::Skybox,
+            color: [0.0, 0.0, 0.0],
+            procedural_sky: false,
+        }
+    }
+}
+
+/// Draws a cubemap behind the scene, as a fullscreen triangle at the far plane
+/// after the opaque geometry. Equirectangular images are converted to a
+/// cubemap on the GPU, one face per instance.
+pub struct Skybox {
+    pub settings: BackgroundSettings,
+    cubemap: texture::Texture,
+    /// Whether `load` found a sky in `res/`.
+    loaded: bool,
+    /// Whether the current sky is the procedural one.
+    procedural: bool,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    color_format: wgpu::TextureFormat,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    converter_bind_group_layout: wgpu::BindGroupLayout,
+    converter_pipeline_layout: wgpu::PipelineLayout,
+    converter_pipeline: wgpu::RenderPipeline,
+}
+
+impl Skybox {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    const FACE_SIZE: u32 = 512;
+    const FLAT_SKY: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+    /// Starts out with a flat grey sky, see `load`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("skybox_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_skybox_pipeline(
+            device,
+            &pipeline_layout,
+            color_format,
+            ShaderId::Skybox.descriptor(ShaderId::Skybox.embedded()),
+        );
+
+        let converter_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("equirect_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                }],
+            });
+
+        let converter_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Equirect Pipeline Layout"),
+                bind_group_layouts: &[&converter_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let converter_pipeline = create_converter_pipeline(
+            device,
+            &converter_pipeline_layout,
+            ShaderId::EquirectToCube.descriptor(ShaderId::EquirectToCube.embedded()),
+        );
+
+        let equirect =
+            texture::Texture::from_rgba32f(device, queue, &Self::FLAT_SKY, 1, 1, "flat_sky");
+        let cubemap = convert_equirect(
+            device,
+            queue,
+            &converter_bind_group_layout,
+            &converter_pipeline,
+            &equirect,
+            1,
+        );
+        let bind_group = create_bind_group(device, &bind_group_layout, &cubemap);
+
+        Self {
+            settings: BackgroundSettings::default(),
+            cubemap,
+            loaded: false,
+            procedural: false,
+            bind_group_layout,
+            bind_group,
+            color_format,
+            pipeline_layout,
+            pipeline,
+            converter_bind_group_layout,
+            converter_pipeline_layout,
+            converter_pipeline,
+        }
+    }
+
+    /// Replaces the flat sky with `res/sky.hdr`, or else the six faces in
+    /// `res/sky/`, if either exists.
+    pub async fn load(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> AppStatus {
+        let hdr_error = match resources::load_hdr_texture(SKY_HDR, device, queue).await {
+            Ok(equirect) => {
+                self.set_equirect(device, queue, &equirect);
+                self.loaded = true;
+                return AppStatus::Info(format!("Loaded the sky from {SKY_HDR}"));
+            }
+            Err(e) => e,
+        };
+
+        match resources::load_cubemap(SKY_FACES, device, queue).await {
+            Ok(cubemap) => {
+                self.set_cubemap(device, cubemap);
+                self.loaded = true;
+                AppStatus::Info("Loaded the sky from sky/".to_owned())
+            }
+            Err(e) => AppStatus::Warning(format!(
+                "No sky found, using a flat one ({SKY_HDR}: {hdr_error}; sky/: {e})"
+            )),
+        }
+    }
+
+    /// Switches between the flat and the procedural sky to follow
+    /// `settings.procedural_sky`, unless a sky was loaded. Returns whether the
+    /// cubemap changed.
+    pub fn sync(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        if self.loaded || self.settings.procedural_sky == self.procedural {
+            return false;
+        }
+        self.procedural = self.settings.procedural_sky;
+
+        let (pixels, width, height) = if self.procedural {
+            let (width, height) = (4 * Self::FACE_SIZE, 2 * Self::FACE_SIZE);
+            (procedural_sky(width, height), width, height)
+        } else {
+            (Self::FLAT_SKY.to_vec(), 1, 1)
+        };
+        let equirect = texture::Texture::from_rgba32f(device, queue, &pixels, width, height, "sky");
+        self.set_equirect(device, queue, &equirect);
+        true
+    }
+
+    pub fn cubemap(&self) -> &texture::Texture {
+        &self.cubemap
+    }
+
+    pub fn set_cubemap(&mut self, device: &wgpu::Device, cubemap: texture::Texture) {
+        self.bind_group = create_bind_group(device, &self.bind_group_layout, &cubemap);
+        self.cubemap = cubemap;
+    }
+
+    pub fn set_equirect(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        equirect: &texture::Texture,
+    ) {
+        // A quarter of the width keeps about one texel per texel at the equator.
+        let face_size = (equirect.texture.width() / 4).clamp(1, 2048);
+        let cubemap = convert_equirect(
+            device,
+            queue,
+            &self.converter_bind_group_layout,
+            &self.converter_pipeline,
+            equirect,
+            face_size,
+        );
+        self.set_cubemap(device, cubemap);
+    }
+
+    /// The converter is only used when a new equirectangular image is set.
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        shader: ShaderId,
+        descriptor: wgpu::ShaderModuleDescriptor,
+    ) -> wgpu::RenderPipeline {
+        match shader {
+            ShaderId::EquirectToCube => {
+                create_converter_pipeline(device, &self.converter_pipeline_layout, descriptor)
+            }
+            _ => {
+                create_skybox_pipeline(device, &self.pipeline_layout, self.color_format, descriptor)
+            }
+        }
+    }
+
+    pub fn set_pipeline(&mut self, shader: ShaderId, pipeline: wgpu::RenderPipeline) {
+        match shader {
+            ShaderId::EquirectToCube => self.converter_pipeline = pipeline,
+            _ => self.pipeline = pipeline,
+        }
+    }
+
+    /// What the scene target is cleared to. The skybox covers it entirely.
+    pub fn clear_color(&self) -> wgpu::Color {
+        match self.settings.mode {
+            BackgroundMode::Color => {
+                let [r, g, b] = self.settings.color;
+                wgpu::Color {
+                    r: r as f64,
+                    g: g as f64,
+                    b: b as f64,
+                    a: 1.0,
+                }
+            }
+            BackgroundMode::Skybox => wgpu::Color::BLACK,
+        }
+    }
+
+    /// Expects the opaque geometry to have been drawn already.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        if self.settings.mode != BackgroundMode::Skybox {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    cubemap: &texture::Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("skybox_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&cubemap.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&cubemap.sampler),
+            },
+        ],
+    })
+}
+
+fn create_skybox_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    // Drawn at depth 1, where the depth buffer was cleared to, so it passes
+    // only where nothing else was drawn.
+    create_render_pipeline(
+        device,
+        layout,
+        color_format,
+        &[],
+        PipelineState {
+            cull_mode: None,
+            depth_format: Some(texture::Texture::DEPTH_FORMAT),
+            depth_write: false,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            ..Default::default()
+        },
+        shader,
+    )
+}
+
+fn create_converter_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    create_render_pipeline(
+        device,
+        layout,
+        Skybox::FORMAT,
+        &[],
+        PipelineState {
+            cull_mode: None,
+            ..Default::default()
+        },
+        shader,
+    )
+}
+
+fn convert_equirect(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    pipeline: &wgpu::RenderPipeline,
+    equirect: &texture::Texture,
+    face_size: u32,
+) -> texture::Texture {
+    let cubemap = texture::Texture::create_cubemap(device, face_size, Skybox::FORMAT, "skybox");
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("equirect_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&equirect.view),
+        }],
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Equirect Encoder"),
+    });
+
+    for face in 0..6 {
+        let view = cubemap.cubemap_face_view(face, 0);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Equirect To Cube Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, face..face + 1);
+    }
+
+    queue.submit(std::iter::once(encoder.finish()));
+
+    cubemap
+}
+
+/// An equirectangular RGBA image of a simple daylight sky with a sun.
+fn procedural_sky(width: u32, height: u32) -> Vec<f32> {
+    let zenith = [0.15, 0.3, 0.8];
+    let horizon = [0.7, 0.8, 0.95];
+    let ground = [0.2, 0.18, 0.15];
+    let sun = Vector3::new(0.4f32, 0.5, -0.6).normalize();
+
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        // Matches the direction to UV mapping in `equirect_to_cube.wgsl`.
+        let theta = (y as f32 + 0.5) / height as f32 * PI;
+        for x in 0..width {
+            let phi = ((x as f32 + 0.5) / width as f32 - 0.5) * 2.0 * PI;
+            let direction = Vector3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            );
+
+            let up = direction.y;
+            let mut color = if up >= 0.0 {
+                let t = up.powf(0.5);
+                [0, 1, 2].map(|i| horizon[i] + (zenith[i] - horizon[i]) * t)
+            } else {
+                let t = (-up * 8.0).min(1.0);
+                [0, 1, 2].map(|i| horizon[i] + (ground[i] - horizon[i]) * t)
+            };
+
+            let cos_sun = direction.dot(sun);
+            let glow = cos_sun.max(0.0).powf(64.0) * 2.0 + cos_sun.max(0.0).powf(2048.0) * 8.0;
+            for channel in &mut color {
+                *channel += glow;
+            }
+
+            pixels.extend_from_slice(&[color[0], color[1], color[2], 1.0]);
+        }
+    }
+
+    pixels
+}
@@ -1,16 +1,104 @@
-use cgmath::{Point3, Vector2, Vector3};
-use egui::{Button, Context, FontDefinitions, FullOutput, RawInput, TopBottomPanel, Vec2};
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use cgmath::{Point3, Quaternion, Vector2, Vector3};
+use egui::{Context, FontDefinitions, FullOutput, RawInput, TopBottomPanel, Vec2};
+use log::Level;
 
 use crate::{
+    bloom::BloomSettings,
     camera::Camera,
+    environment::EnvironmentSettings,
     event::{AppStatus, EventProxy, UserEvent},
+    gizmo::{GizmoMode, GizmoSettings, GizmoSpace},
+    grid::GridSettings,
+    instance::{InstanceGenerator, InstanceLayout, InstanceSet, RotationRule},
+    lights::{Light, LightKind, MAX_LIGHTS},
+    logger::{self, LogRecord},
+    model::{AlphaMode, Material, Model},
     mouse::Mouse,
+    notifications::Notifications,
+    postprocess::{PostSettings, ToneMapping},
+    profiler::{Profiler, Timings},
+    render_mode::RenderMode,
+    render_queue::RenderQueues,
+    renderer::Renderer,
+    scene::{Component, NodeId, Scene},
+    shadow::ShadowSettings,
     shortcut::Shortcut,
+    skybox::{BackgroundMode, BackgroundSettings},
 };
 
 pub struct UI {
     context: Context,
     shortcut: Shortcut,
+    selection: Option<Selection>,
+    notifications: Notifications,
+    show_history: bool,
+    console: Console,
+    show_console: bool,
+    show_profiler: bool,
+    rotation_edit: Option<RotationEdit>,
+    camera_edit: Option<CameraEdit>,
+}
+
+/// Filters of the log console, kept between frames.
+#[derive(Default)]
+struct Console {
+    hidden_levels: HashSet<Level>,
+    search: String,
+    /// Only records with this target, when set.
+    module: Option<String>,
+    /// Stops picking up new records.
+    paused: bool,
+    /// A copy of the captured records, updated with the new ones only.
+    records: VecDeque<LogRecord>,
+    /// `logger::Records::pushed` as of the last update.
+    pushed: u64,
+}
+
+impl Console {
+    /// Copies the records captured since the last call, unless paused.
+    fn update(&mut self) {
+        if self.paused {
+            return;
+        }
+        let records = logger::records();
+        self.records.extend(records.since(self.pushed).cloned());
+        self.records
+            .drain(..self.records.len().saturating_sub(records.records.len()));
+        self.pushed = records.pushed;
+    }
+}
+
+/// Euler angles being edited, kept as typed rather than converted back from
+/// the rotation they made, see `edit_rotation`.
+struct RotationEdit {
+    rotation: Quaternion<f32>,
+    /// In degrees.
+    angles: [f32; 3],
+}
+
+/// A camera edit that is not valid yet, kept until it is or is reverted.
+struct CameraEdit {
+    camera: Camera,
+    error: String,
+}
+
+/// What the outliner and the inspector are showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Selection {
+    Node(NodeId),
+    Mesh { model: usize, mesh: usize },
+    Instance(usize),
+    Light(usize),
+    Points,
+}
+
+/// Applied once the outliner is drawn, since it only borrows the scene.
+enum OutlinerAction {
+    Select(Selection),
+    ToggleVisible(Selection),
+    Delete(Selection),
 }
 
 impl UI {
@@ -22,9 +110,22 @@ impl UI {
         Self {
             context,
             shortcut: sc,
+            selection: None,
+            notifications: Notifications::new(),
+            show_history: false,
+            console: Console::default(),
+            show_console: false,
+            show_profiler: false,
+            rotation_edit: None,
+            camera_edit: None,
         }
     }
 
+    /// Shows `status` as a toast and keeps it in the history.
+    pub fn notify(&mut self, status: AppStatus) {
+        self.notifications.push(status);
+    }
+
     pub fn context(&self) -> &Context {
         &self.context
     }
@@ -34,22 +135,25 @@ impl UI {
         raw_input: RawInput,
         event_proxy: &impl EventProxy<UserEvent>,
         state: &mut UiState,
-        camera: &mut Camera,
         mouse: &mut Mouse,
+        renderer: &mut Renderer,
     ) -> FullOutput {
-        self.context.run(raw_input, |ctx| {
-            self.ui(ctx, event_proxy, state, camera, mouse);
+        let context = self.context.clone();
+        context.run(raw_input, |ctx| {
+            self.ui(ctx, event_proxy, state, mouse, renderer);
         })
     }
 
     fn ui(
-        &self,
+        &mut self,
         ctx: &Context,
         event_proxy: &impl EventProxy<UserEvent>,
         state: &mut UiState,
-        camera: &mut Camera,
         mouse: &mut Mouse,
+        renderer: &mut Renderer,
     ) {
+        let last_pick = renderer.last_pick();
+
         if ctx.input_mut(|i| i.consume_shortcut(&self.shortcut.app_quit)) {
             event_proxy.send_event(UserEvent::Quit);
         }
@@ -70,71 +174,1433 @@ impl UI {
             event_proxy.send_event(UserEvent::SaveFileAs);
         }
 
+        TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                status_label(ui, &state.status);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.toggle_value(&mut self.show_history, "History");
+                    ui.toggle_value(&mut self.show_console, "Console");
+                    ui.toggle_value(&mut self.show_profiler, "Profiler");
+                });
+            });
+        });
+
+        if self.show_console {
+            TopBottomPanel::bottom("console")
+                .resizable(true)
+                .show(ctx, |ui| console(ui, &mut self.console));
+        }
+
+        self.toasts(ctx);
+
+        egui::containers::Window::new("Profiler")
+            .open(&mut self.show_profiler)
+            .show(ctx, |ui| profiler(ui, &mut renderer.profiler));
+
+        egui::containers::Window::new("History")
+            .open(&mut self.show_history)
+            .show(ctx, |ui| {
+                if ui.button("Clear").clicked() {
+                    self.notifications.clear_history();
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        // Newest first.
+                        for notification in self.notifications.history().rev() {
+                            ui.horizontal(|ui| {
+                                let age = notification.time.elapsed().as_secs();
+                                ui.weak(format!("{age}s ago"));
+                                status_label(ui, &notification.status);
+                            });
+                        }
+                    });
+            });
+
         egui::containers::Window::new("Debuger")
             .default_open(true)
             .show(ctx, |ui| {
                 egui::CollapsingHeader::new("Camera").show(ui, |ui| {
-                    egui::Grid::new("debug_camera_grid")
+                    camera_settings(ui, &mut renderer.camera, &mut self.camera_edit);
+                });
+                // Read only, so edits are made to copies.
+                egui::CollapsingHeader::new("Mouse").show(ui, |ui| {
+                    egui::Grid::new("debug_mouse_grid")
                         .num_columns(2)
                         .spacing([10.0, 4.0])
                         .striped(true)
                         .show(ui, |ui| {
-                            ui.label("Eye:");
-                            camera.eye.render_xyz(ui, "eye");
-                            ui.end_row();
-
-                            ui.label("Target:");
-                            camera.target.render_xyz(ui, "target");
-                            ui.end_row();
-
-                            ui.label("Up:");
-                            camera.up.render_xyz(ui, "up");
-                            ui.end_row();
-
-                            ui.label("Aspect:");
-                            ui.add(egui::TextEdit::singleline(&mut camera.aspect.to_string()));
+                            ui.label("viewport:");
+                            ui.add_enabled_ui(false, |ui| {
+                                mouse.pos_viewport().render_xy(ui, "mouse_viewport")
+                            });
                             ui.end_row();
 
-                            ui.label("Fovy:");
-                            ui.add(egui::TextEdit::singleline(&mut camera.fovy.to_string()));
+                            ui.label("Ndc:");
+                            ui.add_enabled_ui(false, |ui| {
+                                mouse.pos_ndc().render_xy(ui, "mouse_ndc")
+                            });
                             ui.end_row();
 
-                            ui.label("Z Near:");
-                            ui.add(egui::TextEdit::singleline(&mut camera.znear.to_string()));
+                            ui.label("Pressed:");
+                            ui.checkbox(&mut mouse.pressed(), "");
                             ui.end_row();
 
-                            ui.label("Z Far:");
-                            ui.add(egui::TextEdit::singleline(&mut camera.zfar.to_string()));
+                            ui.label("Released:");
+                            ui.checkbox(&mut mouse.released(), "");
                             ui.end_row();
                         })
                 });
-                egui::CollapsingHeader::new("Mouse").show(ui, |ui| {
-                    egui::Grid::new("debug_mouse_grid")
+                egui::CollapsingHeader::new("Picking").show(ui, |ui| {
+                    let Some(mut hit) = last_pick else {
+                        ui.label("Click an instance to select it.");
+                        return;
+                    };
+
+                    egui::Grid::new("debug_picking_grid")
                         .num_columns(2)
                         .spacing([10.0, 4.0])
                         .striped(true)
                         .show(ui, |ui| {
-                            ui.label("viewport:");
-                            mouse.pos_viewport().render_xy(ui, "mouse_viewport");
+                            ui.label("Instance:");
+                            ui.label(format!("#{}", hit.instance));
                             ui.end_row();
 
-                            ui.label("Ndc:");
-                            mouse.pos_ndc().render_xy(ui, "mouse_ndc");
+                            ui.label("Position:");
+                            ui.add_enabled_ui(false, |ui| {
+                                hit.position.render_xyz(ui, "pick_position")
+                            });
                             ui.end_row();
 
-                            ui.label("Pressed:");
-                            ui.checkbox(&mut mouse.pressed(), "");
+                            ui.label("Normal:");
+                            ui.add_enabled_ui(false, |ui| hit.normal.render_xyz(ui, "pick_normal"));
                             ui.end_row();
 
-                            ui.label("Released:");
-                            ui.checkbox(&mut mouse.released(), "");
+                            ui.label("Distance:");
+                            ui.label(format!("{:.3}", hit.distance));
                             ui.end_row();
-                        })
+                        });
+                });
+                egui::CollapsingHeader::new("Gizmo").show(ui, |ui| {
+                    gizmo_settings(ui, &mut renderer.gizmo.settings);
+                    ui.checkbox(&mut renderer.lights.show_gizmos, "Show light icons");
+                });
+                egui::CollapsingHeader::new("Grid").show(ui, |ui| {
+                    grid_settings(ui, &mut renderer.grid.settings);
+                });
+                egui::CollapsingHeader::new("Shadows").show(ui, |ui| {
+                    shadow_settings(ui, &mut renderer.shadows.settings);
+                });
+                egui::CollapsingHeader::new("Background").show(ui, |ui| {
+                    background_settings(ui, &mut renderer.skybox.settings);
+                });
+                egui::CollapsingHeader::new("Environment").show(ui, |ui| {
+                    environment_settings(ui, &mut renderer.environment.settings);
+                });
+                egui::CollapsingHeader::new("Post Processing").show(ui, |ui| {
+                    post_settings(ui, &mut renderer.post.settings);
+                });
+                egui::CollapsingHeader::new("Bloom").show(ui, |ui| {
+                    bloom_settings(ui, &mut renderer.post.bloom.settings);
+                });
+                egui::CollapsingHeader::new("Render Mode").show(ui, |ui| {
+                    render_mode(ui, renderer);
+                });
+                egui::CollapsingHeader::new("Culling").show(ui, |ui| {
+                    culling(ui, &mut renderer.queues);
+                });
+                egui::CollapsingHeader::new("Shaders").show(ui, |ui| {
+                    ui.checkbox(&mut renderer.shaders.enabled, "Hot reload from disk");
+                    if ui.button("Reload all").clicked() {
+                        renderer.shaders.enabled = true;
+                        renderer.shaders.reload_all();
+                    }
+                });
+            });
+
+        egui::containers::Window::new("Instances")
+            .default_open(false)
+            .show(ctx, |ui| {
+                let instance_generator = &mut renderer.instances.generator;
+
+                egui::Grid::new("instance_generator_grid")
+                    .num_columns(2)
+                    .spacing([10.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Layout:");
+                        egui::ComboBox::from_id_source("instance_layout")
+                            .selected_text(instance_generator.layout.name())
+                            .show_ui(ui, |ui| {
+                                for layout in InstanceLayout::ALL {
+                                    ui.selectable_value(
+                                        &mut instance_generator.layout,
+                                        layout,
+                                        layout.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Count:");
+                        ui.add(
+                            egui::DragValue::new(&mut instance_generator.count)
+                                .clamp_range(0..=InstanceGenerator::MAX_COUNT),
+                        );
+                        ui.end_row();
+
+                        ui.label("Spacing:");
+                        ui.add(
+                            egui::DragValue::new(&mut instance_generator.spacing)
+                                .speed(0.05)
+                                .clamp_range(0.0..=100.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Rotation:");
+                        egui::ComboBox::from_id_source("instance_rotation")
+                            .selected_text(instance_generator.rotation.name())
+                            .show_ui(ui, |ui| {
+                                for rule in RotationRule::ALL {
+                                    ui.selectable_value(
+                                        &mut instance_generator.rotation,
+                                        rule,
+                                        rule.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Angle:");
+                        ui.add(
+                            egui::DragValue::new(&mut instance_generator.angle)
+                                .suffix("°")
+                                .clamp_range(-360.0..=360.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Scale:");
+                        ui.add(
+                            egui::DragValue::new(&mut instance_generator.scale)
+                                .speed(0.01)
+                                .clamp_range(0.01..=100.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Seed:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut instance_generator.seed));
+                            if ui.button("Reroll").clicked() {
+                                instance_generator.seed = instance_generator
+                                    .seed
+                                    .wrapping_mul(6364136223846793005)
+                                    .wrapping_add(1442695040888963407);
+                            }
+                        });
+                        ui.end_row();
+                    });
+
+                if ui.button("Reset").clicked() {
+                    *instance_generator = InstanceGenerator::default();
+                }
+            });
+
+        self.sync_selection(renderer);
+
+        egui::containers::Window::new("Outliner")
+            .default_open(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Add light:");
+                    ui.add_enabled_ui(renderer.lights.lights.len() < MAX_LIGHTS, |ui| {
+                        for kind in LightKind::ALL {
+                            if ui.button(kind.name()).clicked() {
+                                renderer.add_light(Light::new(kind), Light::default_transform());
+                            }
+                        }
+                    });
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for action in outliner(ui, renderer, self.selection) {
+                            self.apply(renderer, action);
+                        }
+                    });
+            });
+
+        egui::containers::Window::new("Inspector")
+            .default_open(true)
+            .show(ctx, |ui| match self.selection {
+                Some(selection) => inspector(ui, renderer, selection, &mut self.rotation_edit),
+                None => {
+                    ui.label("Nothing selected.");
+                }
+            });
+
+        if renderer.shaders.errors().next().is_some() {
+            egui::containers::Window::new("Shader Errors").show(ctx, |ui| {
+                for error in renderer.shaders.errors() {
+                    let location = match error.location {
+                        Some((line, column)) => {
+                            format!("{}:{}:{}", error.file, line, column)
+                        }
+                        None => error.file.clone(),
+                    };
+
+                    ui.strong(error.shader.label());
+                    ui.colored_label(ui.visuals().error_fg_color, location);
+                    ui.label(egui::RichText::new(&error.message).monospace());
+                    ui.separator();
+                }
+            });
+        }
+    }
+}
+
+impl UI {
+    /// The unexpired notifications, stacked above the status bar.
+    fn toasts(&mut self, ctx: &Context) {
+        self.notifications.expire(std::time::Instant::now());
+
+        let mut dismissed = None;
+        egui::Area::new("toasts")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -40.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (i, toast) in self.notifications.toasts().iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            status_label(ui, &toast.status);
+                            if ui.small_button("🗙").clicked() {
+                                dismissed = Some(i);
+                            }
+                        });
+                    });
+                }
+            });
+
+        if let Some(i) = dismissed {
+            self.notifications.dismiss(i);
+        }
+        if !self.notifications.toasts().is_empty() {
+            // Keeps repainting until the last toast expires.
+            ctx.request_repaint();
+        }
+    }
+
+    /// Follows the instance or light picked in the viewport or chosen in the
+    /// debugger.
+    fn sync_selection(&mut self, renderer: &Renderer) {
+        let picked = renderer
+            .instances
+            .selected()
+            .map(Selection::Instance)
+            .or(renderer.lights.selected().map(Selection::Light));
+
+        match self.selection {
+            None | Some(Selection::Instance(_) | Selection::Light(_)) => self.selection = picked,
+            Some(_) if picked.is_some() => self.selection = picked,
+            Some(_) => {}
+        }
+    }
+
+    fn apply(&mut self, renderer: &mut Renderer, action: OutlinerAction) {
+        match action {
+            OutlinerAction::Select(selection) => {
+                self.selection = Some(selection);
+                renderer.instances.select(match selection {
+                    Selection::Instance(index) => Some(index),
+                    _ => None,
+                });
+                renderer.lights.select(match selection {
+                    Selection::Light(index) => Some(index),
+                    _ => None,
+                });
+            }
+            OutlinerAction::ToggleVisible(selection) => match selection {
+                Selection::Node(id) => {
+                    let node = renderer.scene.node_mut(id);
+                    node.visible = !node.visible;
+                }
+                Selection::Mesh { model, mesh } => {
+                    let mesh = &mut renderer.scene.models[model].meshes[mesh];
+                    mesh.visible = !mesh.visible;
+                }
+                Selection::Instance(index) => {
+                    if let Some(instance) = renderer.instances.get_mut(index) {
+                        instance.visible = !instance.visible;
+                    }
+                }
+                Selection::Light(index) => {
+                    let light = &mut renderer.lights.lights[index];
+                    light.visible = !light.visible;
+                }
+                Selection::Points => {}
+            },
+            OutlinerAction::Delete(selection) => {
+                match selection {
+                    Selection::Node(id) => renderer.remove_node(id),
+                    Selection::Mesh { model, mesh } => renderer.remove_mesh(model, mesh),
+                    Selection::Instance(index) => renderer.instances.remove(index),
+                    Selection::Light(index) => renderer.remove_light(index),
+                    Selection::Points => renderer.clear_points(),
+                }
+                // Indices after the deleted object have moved.
+                self.selection = None;
+                renderer.instances.select(None);
+                renderer.lights.select(None);
+            }
+        }
+    }
+}
+
+/// A tree of the scene's nodes, with the meshes, instances, lights and
+/// points their components hold.
+fn outliner(
+    ui: &mut egui::Ui,
+    renderer: &Renderer,
+    selection: Option<Selection>,
+) -> Vec<OutlinerAction> {
+    let mut actions = Vec::new();
+    for &root in renderer.scene.roots() {
+        outliner_node(ui, renderer, root, selection, &mut actions);
+    }
+    actions
+}
+
+fn outliner_node(
+    ui: &mut egui::Ui,
+    renderer: &Renderer,
+    id: NodeId,
+    selection: Option<Selection>,
+    actions: &mut Vec<OutlinerAction>,
+) {
+    let scene = &renderer.scene;
+    let node = scene.node(id);
+
+    egui::collapsing_header::CollapsingState::load_with_default_open(
+        ui.ctx(),
+        ui.make_persistent_id(("outliner_node", id)),
+        true,
+    )
+    .show_header(ui, |ui| {
+        outliner_row(
+            ui,
+            &node.name,
+            Some(node.visible),
+            Selection::Node(id),
+            selection,
+            actions,
+        );
+    })
+    .body(|ui| {
+        for &child in scene.children(id) {
+            outliner_node(ui, renderer, child, selection, actions);
+        }
+
+        for component in &node.components {
+            match *component {
+                Component::Model { model, instanced } => {
+                    for (m, mesh) in scene.models[model].meshes.iter().enumerate() {
+                        outliner_row(
+                            ui,
+                            &mesh.name,
+                            Some(mesh.visible),
+                            Selection::Mesh { model, mesh: m },
+                            selection,
+                            actions,
+                        );
+                    }
+
+                    if instanced {
+                        let instances = renderer.instances.instances();
+                        egui::CollapsingHeader::new(format!("Instances ({})", instances.len()))
+                            .id_source(("outliner_instances", id))
+                            .show(ui, |ui| {
+                                egui::ScrollArea::vertical()
+                                    .id_source(("outliner_instances_scroll", id))
+                                    .max_height(120.0)
+                                    .show(ui, |ui| {
+                                        for (i, instance) in instances.iter().enumerate() {
+                                            outliner_row(
+                                                ui,
+                                                &format!("Instance #{}", i),
+                                                Some(instance.visible),
+                                                Selection::Instance(i),
+                                                selection,
+                                                actions,
+                                            );
+                                        }
+                                    });
+                            });
+                    }
+                }
+                Component::Light(i) => {
+                    let light = &renderer.lights.lights[i];
+                    outliner_row(
+                        ui,
+                        &format!("{} #{}", light.kind.name(), i),
+                        Some(light.visible),
+                        Selection::Light(i),
+                        selection,
+                        actions,
+                    );
+                }
+                Component::Points => {
+                    outliner_row(
+                        ui,
+                        &format!("{} points", renderer.points()),
+                        None,
+                        Selection::Points,
+                        selection,
+                        actions,
+                    );
+                }
+                Component::Camera => {
+                    ui.weak("Camera");
+                }
+            }
+        }
+    });
+}
+
+/// A selectable label, with a visibility checkbox for objects that can be
+/// hidden and a context menu to delete it.
+fn outliner_row(
+    ui: &mut egui::Ui,
+    label: &str,
+    visible: Option<bool>,
+    target: Selection,
+    selection: Option<Selection>,
+    actions: &mut Vec<OutlinerAction>,
+) {
+    ui.horizontal(|ui| {
+        if let Some(mut visible) = visible {
+            if ui.checkbox(&mut visible, "").changed() {
+                actions.push(OutlinerAction::ToggleVisible(target));
+            }
+        }
+
+        let response = ui.selectable_label(selection == Some(target), label);
+        if response.clicked() {
+            actions.push(OutlinerAction::Select(target));
+        }
+        response.context_menu(|ui| {
+            if ui.button("Delete").clicked() {
+                actions.push(OutlinerAction::Delete(target));
+                ui.close_menu();
+            }
+        });
+    });
+}
+
+fn inspector(
+    ui: &mut egui::Ui,
+    renderer: &mut Renderer,
+    selection: Selection,
+    rotation_edit: &mut Option<RotationEdit>,
+) {
+    match selection {
+        Selection::Node(id) => node_inspector(ui, &mut renderer.scene, id, rotation_edit),
+        Selection::Mesh { model, mesh } => {
+            mesh_inspector(ui, &mut renderer.scene.models[model], mesh);
+        }
+        Selection::Instance(index) => {
+            edit_instance(ui, &mut renderer.instances, index, rotation_edit);
+        }
+        Selection::Light(index) => {
+            edit_light(ui, &mut renderer.lights.lights[index]);
+            // The node places the light.
+            if let Some(node) = renderer.scene.find(Component::Light(index)) {
+                ui.separator();
+                node_inspector(ui, &mut renderer.scene, node, rotation_edit);
+            }
+        }
+        Selection::Points => {
+            ui.label(format!("{} points", renderer.points()));
+            if ui.button("Clear").clicked() {
+                renderer.clear_points();
+            }
+        }
+    }
+}
+
+fn node_inspector(
+    ui: &mut egui::Ui,
+    scene: &mut Scene,
+    id: NodeId,
+    rotation_edit: &mut Option<RotationEdit>,
+) {
+    // Any node outside of this one's subtree can become its parent.
+    let parents = scene
+        .traverse()
+        .into_iter()
+        .filter(|&other| !scene.is_ancestor(id, other))
+        .map(|other| (other, scene.node(other).name.clone()))
+        .collect::<Vec<_>>();
+    let mut parent = scene.parent(id);
+
+    let node = scene.node_mut(id);
+    egui::Grid::new("node_inspector_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut node.name);
+            ui.end_row();
+
+            ui.label("Visible:");
+            ui.checkbox(&mut node.visible, "");
+            ui.end_row();
+
+            ui.label("Translation:");
+            node.transform.translation.edit_xyz(ui, 0.05);
+            ui.end_row();
+
+            ui.label("Rotation:");
+            edit_rotation(ui, &mut node.transform.rotation, rotation_edit);
+            ui.end_row();
+
+            ui.label("Scale:");
+            node.transform.scale.edit_xyz(ui, 0.01);
+            ui.end_row();
+
+            ui.label("Parent:");
+            let name = |parent: Option<NodeId>| {
+                parent
+                    .and_then(|parent| parents.iter().find(|(id, _)| *id == parent))
+                    .map_or("None", |(_, name)| name.as_str())
+                    .to_owned()
+            };
+            egui::ComboBox::from_id_source("node_parent")
+                .selected_text(name(parent))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut parent, None, "None");
+                    for (other, name) in &parents {
+                        ui.selectable_value(&mut parent, Some(*other), name);
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Components:");
+            ui.vertical(|ui| {
+                for component in &node.components {
+                    ui.label(component.name());
+                }
+            });
+            ui.end_row();
+        });
+
+    if parent != scene.parent(id) {
+        scene.set_parent(id, parent);
+    }
+}
+
+fn mesh_inspector(ui: &mut egui::Ui, model: &mut Model, index: usize) {
+    let materials = model
+        .materials
+        .iter()
+        .map(|material| material.name.clone())
+        .collect::<Vec<_>>();
+    let mesh = &mut model.meshes[index];
+
+    egui::Grid::new("mesh_inspector_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut mesh.name);
+            ui.end_row();
+
+            ui.label("Visible:");
+            ui.checkbox(&mut mesh.visible, "");
+            ui.end_row();
+
+            ui.label("Material:");
+            egui::ComboBox::from_id_source("mesh_material")
+                .selected_text(materials[mesh.material].as_str())
+                .show_ui(ui, |ui| {
+                    for (i, name) in materials.iter().enumerate() {
+                        ui.selectable_value(&mut mesh.material, i, name);
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Triangles:");
+            ui.label((mesh.indices.len() / 3).to_string());
+            ui.end_row();
+
+            ui.label("Bounds:");
+            ui.label(format!(
+                "({:.2}, {:.2}, {:.2}) - ({:.2}, {:.2}, {:.2})",
+                mesh.bounds.min.x,
+                mesh.bounds.min.y,
+                mesh.bounds.min.z,
+                mesh.bounds.max.x,
+                mesh.bounds.max.y,
+                mesh.bounds.max.z,
+            ));
+            ui.end_row();
+        });
+
+    let material = mesh.material;
+    ui.separator();
+    ui.label(format!("Material: {}", model.materials[material].name));
+    material_inspector(ui, &mut model.materials[material]);
+}
+
+fn console(ui: &mut egui::Ui, console: &mut Console) {
+    console.update();
+
+    ui.horizontal(|ui| {
+        for level in [Level::Error, Level::Warn, Level::Info] {
+            let mut shown = !console.hidden_levels.contains(&level);
+            if ui.checkbox(&mut shown, level.as_str()).changed() {
+                if shown {
+                    console.hidden_levels.remove(&level);
+                } else {
+                    console.hidden_levels.insert(level);
+                }
+            }
+        }
+        ui.separator();
+
+        let modules = console
+            .records
+            .iter()
+            .map(|record| record.target.as_str())
+            .collect::<BTreeSet<_>>();
+        egui::ComboBox::from_id_source("console_module")
+            .selected_text(console.module.as_deref().unwrap_or("All modules"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut console.module, None, "All modules");
+                for module in modules {
+                    ui.selectable_value(&mut console.module, Some(module.to_owned()), module);
+                }
+            });
+
+        ui.add(egui::TextEdit::singleline(&mut console.search).hint_text("Search"));
+        ui.separator();
+
+        ui.toggle_value(&mut console.paused, "Pause");
+        if ui.button("Clear").clicked() {
+            logger::records().clear();
+            console.records.clear();
+        }
+    });
+
+    let search = console.search.to_lowercase();
+    let shown = console
+        .records
+        .iter()
+        .filter(|record| !console.hidden_levels.contains(&record.level))
+        .filter(|record| console.module.as_ref().is_none_or(|m| *m == record.target))
+        .filter(|record| search.is_empty() || record.message.to_lowercase().contains(&search))
+        .collect::<Vec<_>>();
+
+    if ui.button("Copy").clicked() {
+        let text = shown
+            .iter()
+            .map(|record| {
+                format!(
+                    "[{:.3} {} {}] {}",
+                    record.time.as_secs_f32(),
+                    record.level,
+                    record.target,
+                    record.message
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        ui.output_mut(|output| output.copied_text = text);
+    }
+    ui.separator();
+
+    egui::ScrollArea::vertical()
+        .auto_shrink([false; 2])
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for record in shown {
+                let color = match record.level {
+                    Level::Error => ui.visuals().error_fg_color,
+                    Level::Warn => ui.visuals().warn_fg_color,
+                    _ => ui.visuals().text_color(),
+                };
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("{:>8.3}", record.time.as_secs_f32()));
+                    ui.colored_label(color, record.level.as_str());
+                    ui.weak(&record.target);
+                    ui.label(&record.message);
                 });
+            }
+        });
+}
+
+fn profiler(ui: &mut egui::Ui, profiler: &mut Profiler) {
+    use egui::plot::{Bar, BarChart, Legend, Line, Plot, PlotPoints};
+
+    ui.checkbox(&mut profiler.paused, "Pause");
+
+    let frame = profiler.frame();
+    if let Some(stats) = frame.stats() {
+        ui.label(format!(
+            "{:.1} fps, {:.2} ms (min {:.2}, max {:.2})",
+            1000.0 / stats.avg,
+            stats.avg,
+            stats.min,
+            stats.max
+        ));
+    }
+
+    let line = |timings: &Timings| {
+        let points = timings
+            .samples()
+            .enumerate()
+            .map(|(i, ms)| [i as f64, ms as f64])
+            .collect::<PlotPoints>();
+        Line::new(points).name(timings.name)
+    };
+    let graph = |ui: &mut egui::Ui, id: &str, timings: &[Timings]| {
+        Plot::new(id)
+            .height(120.0)
+            .legend(Legend::default())
+            .include_y(0.0)
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .show_x(false)
+            .show(ui, |plot_ui| {
+                for t in timings {
+                    plot_ui.line(line(t));
+                }
+            });
+    };
+
+    egui::CollapsingHeader::new("Frame")
+        .default_open(true)
+        .show(ui, |ui| {
+            graph(ui, "profiler_frame", std::slice::from_ref(frame));
+
+            let bars = frame.histogram(30).map_or_else(Vec::new, |histogram| {
+                let width = histogram.width as f64;
+                histogram
+                    .bins
+                    .iter()
+                    .map(|&(start, count)| {
+                        Bar::new(start as f64 + width / 2.0, count as f64).width(width)
+                    })
+                    .collect()
             });
+            Plot::new("profiler_histogram")
+                .height(80.0)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .show(ui, |plot_ui| {
+                    plot_ui.bar_chart(BarChart::new(bars).name("Frames per ms"))
+                });
+        });
+
+    egui::CollapsingHeader::new("CPU").show(ui, |ui| {
+        graph(ui, "profiler_cpu", profiler.cpu());
+        timings_grid(ui, "profiler_cpu_grid", profiler.cpu());
+    });
+
+    egui::CollapsingHeader::new("GPU").show(ui, |ui| {
+        if !profiler.has_gpu_timings() {
+            ui.label("Timestamp queries are not supported by this device.");
+            return;
+        }
+        graph(ui, "profiler_gpu", profiler.gpu());
+        timings_grid(ui, "profiler_gpu_grid", profiler.gpu());
+    });
+}
+
+fn timings_grid(ui: &mut egui::Ui, id: &str, timings: &[Timings]) {
+    egui::Grid::new(id)
+        .num_columns(4)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            for header in ["Section", "Min", "Avg", "Max"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+
+            for t in timings {
+                let Some(stats) = t.stats() else {
+                    continue;
+                };
+                ui.label(t.name);
+                for ms in [stats.min, stats.avg, stats.max] {
+                    ui.monospace(format!("{ms:.3} ms"));
+                }
+                ui.end_row();
+            }
+        });
+}
+
+fn status_label(ui: &mut egui::Ui, status: &AppStatus) {
+    let color = match status {
+        AppStatus::Info(_) => ui.visuals().text_color(),
+        AppStatus::Warning(_) => ui.visuals().warn_fg_color,
+        AppStatus::Error(_) => ui.visuals().error_fg_color,
+    };
+    ui.colored_label(color, status.icon());
+    ui.label(status.message());
+}
+
+/// Applies edits once they make a valid camera. Until then they stay in
+/// `edit` with the reason, unless reverted.
+fn camera_settings(ui: &mut egui::Ui, camera: &mut Camera, edit: &mut Option<CameraEdit>) {
+    let mut edited = edit.as_ref().map_or(*camera, |edit| edit.camera);
+    let mut changed = false;
+
+    egui::Grid::new("debug_camera_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Eye:");
+            changed |= edited.eye.render_xyz(ui, "eye");
+            ui.end_row();
+
+            ui.label("Target:");
+            changed |= edited.target.render_xyz(ui, "target");
+            ui.end_row();
+
+            ui.label("Up:");
+            changed |= edited.up.render_xyz(ui, "up");
+            ui.end_row();
+
+            ui.label("Aspect:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut edited.aspect)
+                        .speed(0.01)
+                        .clamp_range(0.01..=100.0),
+                )
+                .changed();
+            ui.end_row();
+
+            ui.label("Fovy:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut edited.fovy)
+                        .suffix("°")
+                        .clamp_range(Camera::FOVY_RANGE),
+                )
+                .changed();
+            ui.end_row();
+
+            ui.label("Z Near:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut edited.znear)
+                        .speed(0.01)
+                        .clamp_range(Camera::MIN_ZNEAR..=f32::MAX),
+                )
+                .changed();
+            ui.end_row();
+
+            ui.label("Z Far:");
+            changed |= ui
+                .add(egui::DragValue::new(&mut edited.zfar).speed(1.0))
+                .changed();
+            ui.end_row();
+        });
+
+    if changed {
+        *edit = match edited.validate() {
+            Ok(()) => {
+                *camera = edited;
+                None
+            }
+            Err(error) => Some(CameraEdit {
+                camera: edited,
+                error,
+            }),
+        };
+    }
+
+    let Some(pending) = edit else {
+        return;
+    };
+    let mut revert = false;
+    ui.horizontal(|ui| {
+        ui.colored_label(ui.visuals().error_fg_color, &pending.error);
+        revert = ui.button("Revert").clicked();
+    });
+    if revert {
+        *edit = None;
     }
 }
 
+fn grid_settings(ui: &mut egui::Ui, grid: &mut GridSettings) {
+    ui.checkbox(&mut grid.enabled, "Show grid");
+    ui.checkbox(&mut grid.show_axes, "Show axes");
+
+    egui::Grid::new("debug_grid_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Spacing:");
+            ui.add(
+                egui::DragValue::new(&mut grid.spacing)
+                    .speed(0.05)
+                    .clamp_range(0.01..=100.0),
+            );
+            ui.end_row();
+
+            ui.label("Major every:");
+            ui.add(egui::DragValue::new(&mut grid.major_every).clamp_range(1..=100));
+            ui.end_row();
+
+            ui.label("Fade distance:");
+            ui.add(
+                egui::DragValue::new(&mut grid.fade_distance)
+                    .speed(0.5)
+                    .clamp_range(1.0..=1000.0),
+            );
+            ui.end_row();
+        });
+
+    if ui.button("Reset").clicked() {
+        *grid = GridSettings::default();
+    }
+}
+
+fn shadow_settings(ui: &mut egui::Ui, shadows: &mut ShadowSettings) {
+    ui.checkbox(&mut shadows.enabled, "Cast shadows");
+
+    egui::Grid::new("debug_shadow_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Light direction:");
+            shadows.light_direction.edit_xyz(ui, 0.01);
+            ui.end_row();
+
+            ui.label("Resolution:");
+            egui::ComboBox::from_id_source("shadow_resolution")
+                .selected_text(shadows.resolution.to_string())
+                .show_ui(ui, |ui| {
+                    for resolution in ShadowSettings::RESOLUTIONS {
+                        ui.selectable_value(
+                            &mut shadows.resolution,
+                            resolution,
+                            resolution.to_string(),
+                        );
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Bias:");
+            ui.add(
+                egui::DragValue::new(&mut shadows.bias)
+                    .speed(0.0001)
+                    .clamp_range(0.0..=0.05),
+            );
+            ui.end_row();
+
+            ui.label("Extent:");
+            ui.add(
+                egui::DragValue::new(&mut shadows.extent)
+                    .speed(0.1)
+                    .clamp_range(1.0..=500.0),
+            );
+            ui.end_row();
+        });
+
+    if ui.button("Reset").clicked() {
+        *shadows = ShadowSettings::default();
+    }
+}
+
+fn background_settings(ui: &mut egui::Ui, background: &mut BackgroundSettings) {
+    for mode in BackgroundMode::ALL {
+        ui.radio_value(&mut background.mode, mode, mode.name());
+    }
+
+    ui.add_enabled_ui(background.mode == BackgroundMode::Color, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Clear color:");
+            ui.color_edit_button_rgb(&mut background.color);
+        });
+    });
+
+    ui.add_enabled_ui(background.mode == BackgroundMode::Skybox, |ui| {
+        ui.checkbox(&mut background.procedural_sky, "Procedural sky")
+            .on_hover_text("Used when res/ has no sky.hdr or sky/ faces");
+    });
+}
+
+fn environment_settings(ui: &mut egui::Ui, environment: &mut EnvironmentSettings) {
+    ui.checkbox(&mut environment.enabled, "Image-based lighting");
+
+    ui.add_enabled_ui(environment.enabled, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Intensity:");
+            ui.add(
+                egui::DragValue::new(&mut environment.intensity)
+                    .speed(0.01)
+                    .clamp_range(0.0..=4.0),
+            );
+        });
+    });
+}
+
+fn post_settings(ui: &mut egui::Ui, post: &mut PostSettings) {
+    egui::Grid::new("debug_post_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.checkbox(&mut post.exposure_enabled, "Exposure (EV):");
+            ui.add_enabled(
+                post.exposure_enabled,
+                egui::DragValue::new(&mut post.exposure)
+                    .speed(0.05)
+                    .clamp_range(-10.0..=10.0),
+            );
+            ui.end_row();
+
+            ui.label("Tone mapping:");
+            egui::ComboBox::from_id_source("post_tone_mapping")
+                .selected_text(post.tone_mapping.name())
+                .show_ui(ui, |ui| {
+                    for tone_mapping in ToneMapping::ALL {
+                        ui.selectable_value(
+                            &mut post.tone_mapping,
+                            tone_mapping,
+                            tone_mapping.name(),
+                        );
+                    }
+                });
+            ui.end_row();
+
+            ui.checkbox(&mut post.gamma_enabled, "Gamma:");
+            ui.add_enabled(
+                post.gamma_enabled,
+                egui::DragValue::new(&mut post.gamma)
+                    .speed(0.01)
+                    .clamp_range(1.0..=3.0),
+            );
+            ui.end_row();
+
+            ui.checkbox(&mut post.fxaa, "FXAA");
+            ui.end_row();
+
+            ui.checkbox(&mut post.vignette, "Vignette strength:");
+            ui.add_enabled(
+                post.vignette,
+                egui::DragValue::new(&mut post.vignette_strength)
+                    .speed(0.01)
+                    .clamp_range(0.0..=1.0),
+            );
+            ui.end_row();
+
+            ui.label("Vignette radius:");
+            ui.add_enabled(
+                post.vignette,
+                egui::DragValue::new(&mut post.vignette_radius)
+                    .speed(0.01)
+                    .clamp_range(0.0..=1.0),
+            );
+            ui.end_row();
+        });
+
+    if ui.button("Reset").clicked() {
+        *post = PostSettings::default();
+    }
+}
+
+fn bloom_settings(ui: &mut egui::Ui, bloom: &mut BloomSettings) {
+    ui.checkbox(&mut bloom.enabled, "Enable bloom");
+
+    egui::Grid::new("debug_bloom_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Intensity:");
+            ui.add(
+                egui::DragValue::new(&mut bloom.intensity)
+                    .speed(0.005)
+                    .clamp_range(0.0..=1.0),
+            );
+            ui.end_row();
+
+            ui.label("Threshold:");
+            ui.add(
+                egui::DragValue::new(&mut bloom.threshold)
+                    .speed(0.01)
+                    .clamp_range(0.0..=10.0),
+            );
+            ui.end_row();
+
+            ui.label("Radius:");
+            ui.add(
+                egui::DragValue::new(&mut bloom.radius)
+                    .speed(0.01)
+                    .clamp_range(0.1..=4.0),
+            );
+            ui.end_row();
+        });
+
+    if ui.button("Reset").clicked() {
+        *bloom = BloomSettings::default();
+    }
+}
+
+fn render_mode(ui: &mut egui::Ui, renderer: &mut Renderer) {
+    for mode in RenderMode::ALL {
+        ui.radio_value(&mut renderer.render_settings.mode, mode, mode.name());
+    }
+
+    if renderer.render_settings.mode == RenderMode::Wireframe {
+        ui.separator();
+        ui.label(if renderer.wireframe.uses_line_mode() {
+            "Edges drawn with PolygonMode::Line."
+        } else {
+            "Line polygon mode unsupported, using the barycentric shader."
+        });
+    }
+}
+
+fn culling(ui: &mut egui::Ui, queues: &mut RenderQueues) {
+    ui.checkbox(&mut queues.culling, "Frustum culling");
+
+    egui::Grid::new("debug_culling_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Visible:");
+            ui.label(queues.stats.visible.to_string());
+            ui.end_row();
+
+            ui.label("Culled:");
+            ui.label(queues.stats.culled.to_string());
+            ui.end_row();
+
+            ui.label("Casters culled:");
+            ui.label(queues.stats.casters_culled.to_string());
+            ui.end_row();
+
+            ui.label("Draw calls:");
+            ui.label((queues.opaque.len() + queues.transparent.len()).to_string());
+            ui.end_row();
+        });
+}
+
+fn gizmo_settings(ui: &mut egui::Ui, gizmo: &mut GizmoSettings) {
+    ui.checkbox(&mut gizmo.enabled, "Show gizmo");
+
+    ui.horizontal(|ui| {
+        for mode in GizmoMode::ALL {
+            ui.selectable_value(&mut gizmo.mode, mode, mode.name());
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut gizmo.space, GizmoSpace::World, "World");
+        ui.selectable_value(&mut gizmo.space, GizmoSpace::Local, "Local");
+    });
+
+    ui.checkbox(&mut gizmo.snap, "Snap");
+    ui.add_enabled_ui(gizmo.snap, |ui| {
+        egui::Grid::new("gizmo_snap_grid")
+            .num_columns(2)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("Translate:");
+                ui.add(
+                    egui::DragValue::new(&mut gizmo.translate_snap)
+                        .speed(0.01)
+                        .clamp_range(0.01..=10.0),
+                );
+                ui.end_row();
+
+                ui.label("Rotate:");
+                ui.add(
+                    egui::DragValue::new(&mut gizmo.rotate_snap)
+                        .suffix("°")
+                        .clamp_range(1.0..=90.0),
+                );
+                ui.end_row();
+
+                ui.label("Scale:");
+                ui.add(
+                    egui::DragValue::new(&mut gizmo.scale_snap)
+                        .speed(0.01)
+                        .clamp_range(0.01..=1.0),
+                );
+                ui.end_row();
+            });
+    });
+}
+
+/// Only writes the instance back when something changed.
+fn edit_instance(
+    ui: &mut egui::Ui,
+    instances: &mut InstanceSet,
+    index: usize,
+    rotation_edit: &mut Option<RotationEdit>,
+) {
+    let Some(mut edited) = instances.instances().get(index).copied() else {
+        return;
+    };
+    let mut changed = false;
+
+    egui::Grid::new("instance_inspector_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Index:");
+            ui.label(index.to_string());
+            ui.end_row();
+
+            ui.label("Visible:");
+            changed |= ui.checkbox(&mut edited.visible, "").changed();
+            ui.end_row();
+
+            ui.label("Position:");
+            changed |= edited.position.edit_xyz(ui, 0.05);
+            ui.end_row();
+
+            ui.label("Rotation:");
+            changed |= edit_rotation(ui, &mut edited.rotation, rotation_edit);
+            ui.end_row();
+
+            ui.label("Scale:");
+            changed |= edited.scale.edit_xyz(ui, 0.01);
+            ui.end_row();
+
+            ui.label("Tint:");
+            changed |= ui
+                .color_edit_button_rgba_unmultiplied(&mut edited.color)
+                .changed();
+            ui.end_row();
+        });
+
+    if changed {
+        if let Some(instance) = instances.get_mut(index) {
+            *instance = edited;
+        }
+    }
+}
+
+/// Edits a rotation as Euler angles in degrees. The angles in `edit` are
+/// reused while the rotation is the one they made, so they are not converted
+/// back every frame, which would make them jump between equivalent sets.
+/// They are derived again when the rotation changed elsewhere.
+fn edit_rotation(
+    ui: &mut egui::Ui,
+    rotation: &mut Quaternion<f32>,
+    edit: &mut Option<RotationEdit>,
+) -> bool {
+    let mut angles = match edit {
+        Some(edit) if edit.rotation == *rotation => edit.angles,
+        _ => {
+            let euler = cgmath::Euler::from(*rotation);
+            [euler.x, euler.y, euler.z].map(|angle| cgmath::Deg::from(angle).0)
+        }
+    };
+
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        for (label, angle) in ["x", "y", "z"].into_iter().zip(&mut angles) {
+            ui.label(label);
+            changed |= ui.add(egui::DragValue::new(angle).suffix("°")).changed();
+        }
+    });
+
+    if changed {
+        let [x, y, z] = angles;
+        *rotation = Quaternion::from(cgmath::Euler::new(
+            cgmath::Deg(x),
+            cgmath::Deg(y),
+            cgmath::Deg(z),
+        ));
+    }
+    *edit = Some(RotationEdit {
+        rotation: *rotation,
+        angles,
+    });
+    changed
+}
+
+fn edit_light(ui: &mut egui::Ui, light: &mut Light) {
+    egui::Grid::new("light_inspector_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Type:");
+            ui.horizontal(|ui| {
+                for kind in LightKind::ALL {
+                    ui.radio_value(&mut light.kind, kind, kind.name());
+                }
+            });
+            ui.end_row();
+
+            ui.label("Visible:");
+            ui.checkbox(&mut light.visible, "");
+            ui.end_row();
+
+            ui.label("Color:");
+            ui.color_edit_button_rgb(&mut light.color);
+            ui.end_row();
+
+            ui.label("Intensity:");
+            ui.add(
+                egui::DragValue::new(&mut light.intensity)
+                    .speed(0.1)
+                    .clamp_range(0.0..=1000.0),
+            );
+            ui.end_row();
+
+            if light.kind != LightKind::Directional {
+                ui.label("Range:");
+                ui.add(
+                    egui::DragValue::new(&mut light.range)
+                        .speed(0.1)
+                        .clamp_range(0.1..=1000.0),
+                );
+                ui.end_row();
+            }
+
+            if light.kind == LightKind::Spot {
+                ui.label("Inner angle:");
+                ui.add(
+                    egui::DragValue::new(&mut light.inner_angle)
+                        .suffix("°")
+                        .clamp_range(0.0..=light.outer_angle),
+                );
+                ui.end_row();
+
+                ui.label("Outer angle:");
+                ui.add(
+                    egui::DragValue::new(&mut light.outer_angle)
+                        .suffix("°")
+                        .clamp_range(1.0..=89.0),
+                );
+                ui.end_row();
+            }
+        });
+}
+
 struct Xyz<T> {
     x: T,
     y: T,
@@ -146,82 +1612,98 @@ struct Xy<T> {
     y: T,
 }
 
+/// Labelled drag values for the components of a 2D vector. Returns whether
+/// one was changed.
 trait XYContent {
     const MIN_RECT: Vec2 = Vec2::new(40., 15.);
-    fn xy(&self) -> Xy<f32>;
-    fn render_xy(&self, ui: &mut egui::Ui, label: &str) {
+    fn xy_mut(&mut self) -> Xy<&mut f32>;
+    fn render_xy(&mut self, ui: &mut egui::Ui, label: &str) -> bool {
+        let Xy { x, y } = self.xy_mut();
+        let mut changed = false;
         egui::Grid::new(label)
             .num_columns(3)
             .min_col_width(5.)
             .spacing([5., 10.])
             .show(ui, |ui| {
-                ui.label("x");
-                ui.add(
-                    egui::TextEdit::singleline(&mut self.xy().x.to_string())
-                        .min_size(Self::MIN_RECT),
-                );
-                ui.label("y");
-                ui.add(
-                    egui::TextEdit::singleline(&mut self.xy().y.to_string())
-                        .min_size(Self::MIN_RECT),
-                );
+                for (name, value) in [("x", x), ("y", y)] {
+                    ui.label(name);
+                    changed |= ui
+                        .add_sized(Self::MIN_RECT, egui::DragValue::new(value).speed(0.01))
+                        .changed();
+                }
             });
+        changed
     }
 }
 
+/// Labelled drag values for the components of a 3D vector or point. Returns
+/// whether one was changed.
 trait XYZContent {
     const MIN_RECT: Vec2 = Vec2::new(40., 15.);
-    fn xyz(&self) -> Xyz<f32>;
-    fn render_xyz(&self, ui: &mut egui::Ui, label: &str) {
+    fn xyz_mut(&mut self) -> Xyz<&mut f32>;
+    fn render_xyz(&mut self, ui: &mut egui::Ui, label: &str) -> bool {
+        let Xyz { x, y, z } = self.xyz_mut();
+        let mut changed = false;
         egui::Grid::new(label)
             .num_columns(3)
             .min_col_width(3.)
             .spacing([10., 10.])
             .show(ui, |ui| {
-                ui.label("x");
-                ui.add(
-                    egui::TextEdit::singleline(&mut self.xyz().x.to_string())
-                        .min_size(Self::MIN_RECT),
-                );
-                ui.label("y");
-                ui.add(
-                    egui::TextEdit::singleline(&mut self.xyz().y.to_string())
-                        .min_size(Self::MIN_RECT),
-                );
-                ui.label("z");
-                ui.add(
-                    egui::TextEdit::singleline(&mut self.xyz().z.to_string())
-                        .min_size(Self::MIN_RECT),
-                );
+                for (name, value) in [("x", x), ("y", y), ("z", z)] {
+                    ui.label(name);
+                    changed |= ui
+                        .add_sized(Self::MIN_RECT, egui::DragValue::new(value).speed(0.01))
+                        .changed();
+                }
             });
+        changed
+    }
+}
+
+/// A compact, single line variant of `XYZContent::render_xyz`.
+trait EditXyz {
+    fn edit_xyz(&mut self, ui: &mut egui::Ui, speed: f64) -> bool;
+}
+
+impl<T: XYZContent> EditXyz for T {
+    fn edit_xyz(&mut self, ui: &mut egui::Ui, speed: f64) -> bool {
+        let Xyz { x, y, z } = self.xyz_mut();
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            for (label, value) in [("x", x), ("y", y), ("z", z)] {
+                ui.label(label);
+                changed |= ui.add(egui::DragValue::new(value).speed(speed)).changed();
+            }
+        });
+        changed
     }
 }
 
 impl XYContent for Vector2<f32> {
-    fn xy(&self) -> Xy<f32> {
+    fn xy_mut(&mut self) -> Xy<&mut f32> {
         Xy {
-            x: self.x,
-            y: self.y,
+            x: &mut self.x,
+            y: &mut self.y,
         }
     }
 }
 
 impl XYZContent for Point3<f32> {
-    fn xyz(&self) -> Xyz<f32> {
+    fn xyz_mut(&mut self) -> Xyz<&mut f32> {
         Xyz {
-            x: self.x,
-            y: self.y,
-            z: self.z,
+            x: &mut self.x,
+            y: &mut self.y,
+            z: &mut self.z,
         }
     }
 }
 
 impl XYZContent for Vector3<f32> {
-    fn xyz(&self) -> Xyz<f32> {
+    fn xyz_mut(&mut self) -> Xyz<&mut f32> {
         Xyz {
-            x: self.x,
-            y: self.y,
-            z: self.z,
+            x: &mut self.x,
+            y: &mut self.y,
+            z: &mut self.z,
         }
     }
 }
@@ -231,6 +1713,46 @@ pub struct UiState {
     pub status: AppStatus,
 }
 
+fn material_inspector(ui: &mut egui::Ui, material: &mut Material) {
+    let factors = &mut material.factors;
+    egui::Grid::new("material_inspector_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Alpha mode:");
+            ui.horizontal(|ui| {
+                // The cutoff is kept when Mask is clicked again.
+                let mode = &mut factors.alpha_mode;
+                for option in [AlphaMode::Opaque, AlphaMode::Mask(0.5), AlphaMode::Blend] {
+                    let selected = std::mem::discriminant(mode) == std::mem::discriminant(&option);
+                    if ui.radio(selected, option.name()).clicked() && !selected {
+                        *mode = option;
+                    }
+                }
+            });
+            ui.end_row();
+
+            if let AlphaMode::Mask(cutoff) = &mut factors.alpha_mode {
+                ui.label("Cutoff:");
+                ui.add(
+                    egui::DragValue::new(cutoff)
+                        .speed(0.01)
+                        .clamp_range(0.0..=1.0),
+                );
+                ui.end_row();
+            }
+
+            ui.label("Opacity:");
+            ui.add(
+                egui::DragValue::new(&mut factors.base_color[3])
+                    .speed(0.01)
+                    .clamp_range(0.0..=1.0),
+            );
+            ui.end_row();
+        });
+}
+
 fn setup_fonts(ctx: &mut Context) {
     let mut fonts = FontDefinitions::default();
 
@@ -0,0 +1,198 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    instance::InstanceRaw,
+    model::{Model, Vertex},
+    render_queue::Draw,
+    renderer::{create_render_pipeline, PipelineState},
+    shaders::ShaderId,
+    texture,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct WireframeVertex {
+    pub position: [f32; 3],
+}
+
+impl Vertex for WireframeVertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<WireframeVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: wgpu::VertexFormat::Float32x3,
+            }],
+        }
+    }
+}
+
+/// Draws the edges of the scene's models on top of the scene. Uses `PolygonMode::Line`
+/// when the device supports it and a barycentric shader otherwise; both read
+/// from unindexed copies of the meshes.
+pub struct Wireframe {
+    polygon_mode: wgpu::PolygonMode,
+    color_format: wgpu::TextureFormat,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    /// Per model, per mesh.
+    meshes: Vec<Vec<(wgpu::Buffer, u32)>>,
+}
+
+impl Wireframe {
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let polygon_mode = if device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+        {
+            wgpu::PolygonMode::Line
+        } else {
+            wgpu::PolygonMode::Fill
+        };
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Wireframe Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_wireframe_pipeline(
+            device,
+            &pipeline_layout,
+            polygon_mode,
+            color_format,
+            ShaderId::Wireframe.descriptor(ShaderId::Wireframe.embedded()),
+        );
+
+        Self {
+            polygon_mode,
+            color_format,
+            pipeline_layout,
+            pipeline,
+            meshes: Vec::new(),
+        }
+    }
+
+    /// Makes the unindexed copies of a model added to the scene.
+    pub fn add_model(&mut self, device: &wgpu::Device, model: &Model) {
+        let meshes = model
+            .meshes
+            .iter()
+            .map(|mesh| {
+                let vertices = mesh
+                    .indices
+                    .iter()
+                    .map(|&i| WireframeVertex {
+                        position: mesh.positions[i as usize],
+                    })
+                    .collect::<Vec<_>>();
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("{} Wireframe Buffer", mesh.name)),
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                });
+
+                (buffer, vertices.len() as u32)
+            })
+            .collect();
+        self.meshes.push(meshes);
+    }
+
+    pub fn remove_mesh(&mut self, model: usize, mesh: usize) {
+        self.meshes[model].remove(mesh);
+    }
+
+    /// Whether edges are rasterized as lines rather than found in the shader.
+    pub fn uses_line_mode(&self) -> bool {
+        self.polygon_mode == wgpu::PolygonMode::Line
+    }
+
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        shader: wgpu::ShaderModuleDescriptor,
+    ) -> wgpu::RenderPipeline {
+        create_wireframe_pipeline(
+            device,
+            &self.pipeline_layout,
+            self.polygon_mode,
+            self.color_format,
+            shader,
+        )
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: wgpu::RenderPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    /// Expects the instance buffer the draws index into to be bound to slot 1
+    /// already.
+    pub fn draw<'a, 'b>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        draws: impl IntoIterator<Item = &'b Draw>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+
+        for draw in draws {
+            let (buffer, vertex_count) = &self.meshes[draw.model][draw.mesh];
+            render_pass.set_vertex_buffer(0, buffer.slice(..));
+            render_pass.draw(0..*vertex_count, draw.instances.clone());
+        }
+    }
+}
+
+fn create_wireframe_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    polygon_mode: wgpu::PolygonMode,
+    color_format: wgpu::TextureFormat,
+    shader: wgpu::ShaderModuleDescriptor,
+) -> wgpu::RenderPipeline {
+    create_render_pipeline(
+        device,
+        layout,
+        color_format,
+        &[WireframeVertex::desc(), InstanceRaw::desc()],
+        PipelineState {
+            polygon_mode,
+            depth_format: Some(texture::Texture::DEPTH_FORMAT),
+            ..Default::default()
+        },
+        shader,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::offset_of;
+
+    use super::WireframeVertex;
+    use crate::instance::InstanceRaw;
+    use crate::layout;
+    use crate::model::Vertex;
+    use crate::shaders::ShaderId;
+
+    #[test]
+    fn wireframe_vertex_matches_wgsl() {
+        layout::assert_buffer_matches::<WireframeVertex>(
+            &WireframeVertex::desc(),
+            &[(0, offset_of!(WireframeVertex, position))],
+        );
+        layout::assert_vertex_inputs(
+            ShaderId::Wireframe,
+            "vs_main",
+            &[WireframeVertex::desc(), InstanceRaw::desc()],
+        );
+    }
+}